@@ -0,0 +1,209 @@
+use std::fmt;
+
+pub const USAGE: &str = "usage: torgi-excel [--config <path>] [command]
+
+commands:
+    watch                      watch the workbook and send changes (default)
+    snapshot [-o <file>]       print active state records as JSON
+    diff <old.json> <workbook> compare old snapshot with the workbook
+    send <file.json>           send JSON payload to the app url
+    validate                   check the workbook columns
+    report [-o <file>]         print problem cells of active records as JSON";
+
+/// Subcommand of the program
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Watch,
+    Snapshot { output: Option<String> },
+    Diff { old: String, workbook: String },
+    Send { file: String },
+    Validate,
+    Report { output: Option<String> },
+}
+
+/// Parsed command line
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+    pub config: Option<String>,
+    pub command: Command,
+}
+
+/// CliError is error type for this module
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    Help,
+    UnknownCommand(String),
+    UnexpectedArgument(String),
+    MissingArgument(&'static str),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Help => write!(f, "{}", USAGE),
+            CliError::UnknownCommand(s) => write!(f, "unknown command '{}'\n{}", s, USAGE),
+            CliError::UnexpectedArgument(s) => write!(f, "unexpected argument '{}'\n{}", s, USAGE),
+            CliError::MissingArgument(s) => write!(f, "missing argument <{}>\n{}", s, USAGE),
+        }
+    }
+}
+
+/// Parses command line arguments (without program name)
+pub fn parse<I>(args: I) -> Result<Cli, CliError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut config = None;
+    let mut output = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Err(CliError::Help),
+            "--config" => config = Some(args.next().ok_or(CliError::MissingArgument("path"))?),
+            "-o" | "--output" => {
+                output = Some(args.next().ok_or(CliError::MissingArgument("file"))?)
+            }
+            s if s.starts_with("--config=") => config = Some(s["--config=".len()..].to_owned()),
+            s if s.starts_with("--output=") => output = Some(s["--output=".len()..].to_owned()),
+            s if s.starts_with('-') => return Err(CliError::UnexpectedArgument(arg)),
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let command = match positional.next().as_deref() {
+        None | Some("watch") => Command::Watch,
+        Some("snapshot") => Command::Snapshot {
+            output: output.take(),
+        },
+        Some("diff") => Command::Diff {
+            old: positional
+                .next()
+                .ok_or(CliError::MissingArgument("old.json"))?,
+            workbook: positional
+                .next()
+                .ok_or(CliError::MissingArgument("workbook"))?,
+        },
+        Some("send") => Command::Send {
+            file: positional
+                .next()
+                .ok_or(CliError::MissingArgument("file.json"))?,
+        },
+        Some("validate") => Command::Validate,
+        Some("report") => Command::Report {
+            output: output.take(),
+        },
+        Some(s) => return Err(CliError::UnknownCommand(s.to_owned())),
+    };
+
+    if let Some(arg) = positional.next() {
+        return Err(CliError::UnexpectedArgument(arg));
+    }
+    if let Some(o) = output {
+        return Err(CliError::UnexpectedArgument(o));
+    }
+
+    Ok(Cli { config, command })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(args("")),
+            Ok(Cli {
+                config: None,
+                command: Command::Watch
+            })
+        );
+        assert_eq!(
+            parse(args("--config c.toml watch")),
+            Ok(Cli {
+                config: Some("c.toml".to_owned()),
+                command: Command::Watch
+            })
+        );
+        assert_eq!(
+            parse(args("snapshot -o out.json --config=c.toml")),
+            Ok(Cli {
+                config: Some("c.toml".to_owned()),
+                command: Command::Snapshot {
+                    output: Some("out.json".to_owned())
+                }
+            })
+        );
+        assert_eq!(
+            parse(args("diff old.json reg.xlsx")),
+            Ok(Cli {
+                config: None,
+                command: Command::Diff {
+                    old: "old.json".to_owned(),
+                    workbook: "reg.xlsx".to_owned()
+                }
+            })
+        );
+        assert_eq!(
+            parse(args("send payload.json")),
+            Ok(Cli {
+                config: None,
+                command: Command::Send {
+                    file: "payload.json".to_owned()
+                }
+            })
+        );
+        assert_eq!(
+            parse(args("validate")),
+            Ok(Cli {
+                config: None,
+                command: Command::Validate
+            })
+        );
+        assert_eq!(
+            parse(args("report -o report.json")),
+            Ok(Cli {
+                config: None,
+                command: Command::Report {
+                    output: Some("report.json".to_owned())
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(args("--help")), Err(CliError::Help));
+        assert_eq!(
+            parse(args("run")),
+            Err(CliError::UnknownCommand("run".to_owned()))
+        );
+        assert_eq!(
+            parse(args("diff old.json")),
+            Err(CliError::MissingArgument("workbook"))
+        );
+        assert_eq!(
+            parse(args("send")),
+            Err(CliError::MissingArgument("file.json"))
+        );
+        assert_eq!(
+            parse(args("validate extra")),
+            Err(CliError::UnexpectedArgument("extra".to_owned()))
+        );
+        assert_eq!(
+            parse(args("watch -o out.json")),
+            Err(CliError::UnexpectedArgument("out.json".to_owned()))
+        );
+        assert_eq!(
+            parse(args("watch --verbose")),
+            Err(CliError::UnexpectedArgument("--verbose".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,725 @@
+use crate::{
+    excel::DateSystem,
+    payload::Payload,
+    schema::{Columns, Duplicates, Extras, Schema, Statuses},
+    simple_time::Offset,
+    source::{CsvOptions, SourceKind},
+};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    env, fmt, fs,
+    path::Path,
+};
+
+/// Default config file that is looked up in the working directory
+/// if no `--config` flag is given
+pub const DEFAULT_CONFIG_PATH: &str = "torgi-excel.toml";
+
+/// Daemon sleep interval in seconds
+const DEFAULT_TIME_TO_SLEEP: u64 = 30;
+const DEFAULT_TEMP_FILE_PATH: &str = "temp.json";
+// name of the watched workbook when there is no [[watch]] section
+const DEFAULT_TARGET_NAME: &str = "main";
+const DEFAULT_RETRY_THRESHOLD: i32 = 20;
+// we don't expect that workbook
+// has a number of active rows greater than that
+const DEFAULT_WORKBOOK_MAX_ROWS: usize = 7000;
+// maximum days that we want to look in past
+// searching for rows
+const DEFAULT_HOW_FAR_IN_PAST_DAYS: u64 = 16;
+const DEFAULT_HEADER_ROW: u32 = 1;
+
+const ENV_WORKBOOK_PATH: &str = "REG_WORKBOOK_PATH";
+const ENV_APP_URL: &str = "TGBOT_APP_URL";
+const ENV_REPORT_URL: &str = "TGBOT_REPORT_URL";
+const ENV_TIME_TO_SLEEP: &str = "TIME_TO_SLEEP";
+const ENV_TEMP_FILE_PATH: &str = "TEMP_FILE_PATH";
+const ENV_RETRY_THRESHOLD: &str = "RETRY_THRESHOLD";
+const ENV_WORKBOOK_MAX_ROWS: &str = "WORKBOOK_MAX_ROWS";
+const ENV_HOW_FAR_IN_PAST_DAYS: &str = "HOW_FAR_IN_PAST_DAYS";
+
+/// Alias result type for this module
+type ConfigResult<T> = std::result::Result<T, ConfigError>;
+
+/// ConfigError is error type for this module
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum ConfigError {
+    IoError(String, std::io::Error),
+    ParseError(String, toml::de::Error),
+    MissingValueError(&'static str),
+    InvalidValueError(&'static str, String),
+    WatchError(String, Box<ConfigError>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::IoError(p, e) => write!(f, "cannot read config file '{}': {}", p, e),
+            ConfigError::ParseError(p, e) => write!(f, "cannot parse config file '{}': {}", p, e),
+            ConfigError::MissingValueError(k) => write!(f, "config value '{}' must be set", k),
+            ConfigError::InvalidValueError(k, reason) => {
+                write!(f, "invalid config value '{}': {}", k, reason)
+            }
+            ConfigError::WatchError(name, e) => write!(f, "watch '{}': {}", name, e),
+        }
+    }
+}
+
+/// Program configuration. It is read from the TOML file
+/// and then every value may be overridden by environment variable
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub workbook: WorkbookConfig,
+    pub daemon: DaemonConfig,
+    pub watch: Vec<WatchConfig>,
+    // environment variables of the single workbook that are applied
+    #[serde(skip)]
+    single_overrides: Vec<&'static str>,
+}
+
+/// Settings of the workbook reader
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkbookConfig {
+    pub path: String,
+    pub max_rows: usize,
+    pub how_far_in_past_days: u64,
+    pub schema: Schema,
+    pub statuses: Statuses,
+    pub columns: Columns,
+    // sheet to read if it can't be determined by named ranges
+    pub sheet: Option<String>,
+    // number of the row with column headers, starts from 1
+    pub header_row: u32,
+    pub headers: Schema,
+    // detected by the path extension if not set
+    pub source: Option<SourceKind>,
+    pub csv: CsvOptions,
+    // detected by the workbook properties if not set
+    pub date_system: Option<DateSystem>,
+    pub duplicates: Duplicates,
+    // add the address of every field cell to the records
+    pub cell_addresses: bool,
+    // time zone of the dates and times that are typed in the workbook
+    pub timezone: Offset,
+    // regions which dates and times are typed in their local time
+    pub region_timezones: HashMap<String, Offset>,
+    // columns that are passed to the records as they are
+    pub extra: Extras,
+}
+
+impl Default for WorkbookConfig {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            max_rows: DEFAULT_WORKBOOK_MAX_ROWS,
+            how_far_in_past_days: DEFAULT_HOW_FAR_IN_PAST_DAYS,
+            schema: Schema::default(),
+            statuses: Statuses::default(),
+            columns: Columns::default(),
+            sheet: None,
+            header_row: DEFAULT_HEADER_ROW,
+            headers: Schema::default(),
+            source: None,
+            csv: CsvOptions::default(),
+            date_system: None,
+            duplicates: Duplicates::default(),
+            cell_addresses: false,
+            timezone: Offset::MSK,
+            region_timezones: HashMap::new(),
+            extra: Extras::default(),
+        }
+    }
+}
+
+impl WorkbookConfig {
+    /// Returns the time zone of the record from the region. Regions
+    /// are compared case insensitively ignoring repeated whitespaces
+    pub fn offset(&self, region: &str) -> Offset {
+        if self.region_timezones.is_empty() {
+            return self.timezone;
+        }
+        let normalize = |s: &str| {
+            s.split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase()
+        };
+        let region = normalize(region);
+        self.region_timezones
+            .iter()
+            .find(|(r, _)| normalize(r) == region)
+            .map(|(_, offset)| *offset)
+            .unwrap_or(self.timezone)
+    }
+}
+
+/// Settings of the watching daemon
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    pub app_url: String,
+    // url for the report of problem cells, it's not sent if empty
+    pub report_url: String,
+    pub time_to_sleep: u64,
+    pub temp_file_path: String,
+    pub retry_threshold: i32,
+    // envelope with metadata or bare array of records
+    pub payload: Payload,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            app_url: String::new(),
+            report_url: String::new(),
+            time_to_sleep: DEFAULT_TIME_TO_SLEEP,
+            temp_file_path: DEFAULT_TEMP_FILE_PATH.to_owned(),
+            retry_threshold: DEFAULT_RETRY_THRESHOLD,
+            payload: Payload::default(),
+        }
+    }
+}
+
+impl DaemonConfig {
+    /// Checks that daemon settings make sense
+    fn validate(&self) -> ConfigResult<()> {
+        check_url("daemon.app_url", &self.app_url)?;
+        check_url("daemon.report_url", &self.report_url)?;
+        if self.temp_file_path.is_empty() {
+            return Err(ConfigError::MissingValueError("daemon.temp_file_path"));
+        }
+        if self.time_to_sleep == 0 {
+            return Err(ConfigError::InvalidValueError(
+                "daemon.time_to_sleep",
+                "must be greater than 0".to_owned(),
+            ));
+        }
+        if self.retry_threshold <= 0 {
+            return Err(ConfigError::InvalidValueError(
+                "daemon.retry_threshold",
+                "must be greater than 0".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// One of the workbooks that are watched by daemon.
+/// Values that are not set are taken
+/// from the `[workbook]` and `[daemon]` sections
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WatchConfig {
+    pub name: String,
+    pub path: String,
+    pub app_url: Option<String>,
+    pub report_url: Option<String>,
+    pub time_to_sleep: Option<u64>,
+    pub temp_file_path: Option<String>,
+    pub retry_threshold: Option<i32>,
+    pub payload: Option<Payload>,
+}
+
+/// Resolved settings of one watched workbook
+#[derive(Debug)]
+pub struct Target {
+    pub name: String,
+    pub workbook: WorkbookConfig,
+    pub daemon: DaemonConfig,
+}
+
+impl Config {
+    /// Builds up configuration. If `path` is given than config file
+    /// must exist, otherwise [DEFAULT_CONFIG_PATH] is read if it exists.
+    /// After that environment variables are applied and result is validated
+    pub fn load(path: Option<&str>) -> ConfigResult<Self> {
+        let mut config = match path {
+            Some(p) => Self::from_file(Path::new(p))?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Self::default(),
+        };
+
+        config.override_from(|k| env::var(k).ok())?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Reads config from TOML file
+    fn from_file(path: &Path) -> ConfigResult<Self> {
+        let display = path.display().to_string();
+        let s = fs::read_to_string(path).map_err(|e| ConfigError::IoError(display.clone(), e))?;
+        toml::from_str(&s).map_err(|e| ConfigError::ParseError(display, e))
+    }
+
+    /// Overrides config values with those that are
+    /// returned by `var` function e.g. environment variables
+    fn override_from<F>(&mut self, var: F) -> ConfigResult<()>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(v) = var(ENV_WORKBOOK_PATH) {
+            self.workbook.path = v;
+            self.single_overrides.push(ENV_WORKBOOK_PATH);
+        }
+        if let Some(v) = var(ENV_APP_URL) {
+            self.daemon.app_url = v;
+            self.single_overrides.push(ENV_APP_URL);
+        }
+        if let Some(v) = var(ENV_REPORT_URL) {
+            self.daemon.report_url = v;
+        }
+        if let Some(v) = var(ENV_TEMP_FILE_PATH) {
+            self.daemon.temp_file_path = v;
+            self.single_overrides.push(ENV_TEMP_FILE_PATH);
+        }
+        if let Some(v) = var(ENV_TIME_TO_SLEEP) {
+            self.daemon.time_to_sleep = parse_var(ENV_TIME_TO_SLEEP, &v)?;
+        }
+        if let Some(v) = var(ENV_RETRY_THRESHOLD) {
+            self.daemon.retry_threshold = parse_var(ENV_RETRY_THRESHOLD, &v)?;
+        }
+        if let Some(v) = var(ENV_WORKBOOK_MAX_ROWS) {
+            self.workbook.max_rows = parse_var(ENV_WORKBOOK_MAX_ROWS, &v)?;
+        }
+        if let Some(v) = var(ENV_HOW_FAR_IN_PAST_DAYS) {
+            self.workbook.how_far_in_past_days = parse_var(ENV_HOW_FAR_IN_PAST_DAYS, &v)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the workbook path is set.
+    /// Not every command needs it, so it is not the part of [Config::load]
+    pub fn require_workbook(&self) -> ConfigResult<()> {
+        if self.workbook.path.is_empty() {
+            return Err(ConfigError::MissingValueError("workbook.path"));
+        }
+        Ok(())
+    }
+
+    /// Checks that the app url is set.
+    /// Not every command needs it, so it is not the part of [Config::load]
+    pub fn require_app_url(&self) -> ConfigResult<()> {
+        if self.daemon.app_url.is_empty() {
+            return Err(ConfigError::MissingValueError("daemon.app_url"));
+        }
+        Ok(())
+    }
+
+    /// Returns the workbooks that daemon has to watch. If there is no
+    /// `[[watch]]` sections than `[workbook]` and `[daemon]` are the only target.
+    /// Environment variables of the single workbook can't tell which
+    /// of the watched ones they are for, so they fail
+    pub fn targets(&self) -> ConfigResult<Vec<Target>> {
+        if let (Some(var), false) = (self.single_overrides.first(), self.watch.is_empty()) {
+            return Err(ConfigError::InvalidValueError(
+                var,
+                "can't be used with [[watch]] sections, set it in the section instead".to_owned(),
+            ));
+        }
+        if self.watch.is_empty() {
+            self.require_workbook()?;
+            self.require_app_url()?;
+            return Ok(vec![Target {
+                name: DEFAULT_TARGET_NAME.to_owned(),
+                workbook: self.workbook.clone(),
+                daemon: self.daemon.clone(),
+            }]);
+        }
+
+        let mut targets = Vec::with_capacity(self.watch.len());
+        let mut names = HashSet::new();
+        let mut temp_files = HashSet::new();
+
+        for w in self.watch.iter() {
+            if w.name.is_empty() {
+                return Err(ConfigError::MissingValueError("watch.name"));
+            }
+            if !names.insert(&w.name) {
+                return Err(ConfigError::InvalidValueError(
+                    "watch.name",
+                    format!("'{}' is used more than once", w.name),
+                ));
+            }
+            if w.path.is_empty() {
+                return Err(ConfigError::MissingValueError("watch.path"));
+            }
+
+            let mut workbook = self.workbook.clone();
+            workbook.path = w.path.clone();
+
+            let daemon = DaemonConfig {
+                app_url: w
+                    .app_url
+                    .clone()
+                    .unwrap_or_else(|| self.daemon.app_url.clone()),
+                report_url: w
+                    .report_url
+                    .clone()
+                    .unwrap_or_else(|| self.daemon.report_url.clone()),
+                time_to_sleep: w.time_to_sleep.unwrap_or(self.daemon.time_to_sleep),
+                // every target needs it's own store
+                temp_file_path: w
+                    .temp_file_path
+                    .clone()
+                    .unwrap_or_else(|| format!("temp_{}.json", w.name)),
+                retry_threshold: w.retry_threshold.unwrap_or(self.daemon.retry_threshold),
+                payload: w.payload.unwrap_or(self.daemon.payload),
+            };
+            if daemon.app_url.is_empty() {
+                return Err(ConfigError::WatchError(
+                    w.name.clone(),
+                    Box::new(ConfigError::MissingValueError("daemon.app_url")),
+                ));
+            }
+            daemon
+                .validate()
+                .map_err(|e| ConfigError::WatchError(w.name.clone(), Box::new(e)))?;
+
+            if !temp_files.insert(daemon.temp_file_path.clone()) {
+                return Err(ConfigError::InvalidValueError(
+                    "watch.temp_file_path",
+                    format!("'{}' is used more than once", daemon.temp_file_path),
+                ));
+            }
+
+            targets.push(Target {
+                name: w.name.clone(),
+                workbook,
+                daemon,
+            });
+        }
+
+        Ok(targets)
+    }
+
+    /// Checks that config values make sense
+    fn validate(&self) -> ConfigResult<()> {
+        self.daemon.validate()?;
+        self.workbook
+            .statuses
+            .validate()
+            .map_err(|e| ConfigError::InvalidValueError("workbook.statuses", e))?;
+        self.workbook
+            .csv
+            .validate()
+            .map_err(|e| ConfigError::InvalidValueError("workbook.csv", e))?;
+        self.workbook
+            .extra
+            .validate(&[&self.workbook.schema, &self.workbook.headers])
+            .map_err(|e| ConfigError::InvalidValueError("workbook.extra", e))?;
+        if self.workbook.header_row == 0 {
+            return Err(ConfigError::InvalidValueError(
+                "workbook.header_row",
+                "rows are numbered from 1".to_owned(),
+            ));
+        }
+        if self.workbook.max_rows == 0 {
+            return Err(ConfigError::InvalidValueError(
+                "workbook.max_rows",
+                "must be greater than 0".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Checks that non-empty url is a http(s) url
+fn check_url(key: &'static str, url: &str) -> ConfigResult<()> {
+    if !url.is_empty() && !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(ConfigError::InvalidValueError(
+            key,
+            format!("'{}' is not a http(s) url", url),
+        ));
+    }
+    Ok(())
+}
+
+/// Parses environment variable value
+fn parse_var<T: std::str::FromStr>(key: &'static str, v: &str) -> ConfigResult<T> {
+    v.trim()
+        .parse()
+        .map_err(|_| ConfigError::InvalidValueError(key, format!("cannot parse '{}'", v)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Field;
+
+    const CONFIG: &str = r#"
+        [workbook]
+        path = "reg.xlsx"
+        max_rows = 100
+
+        [workbook.schema]
+        registry_number = ["Номер", "Номер_извещения"]
+
+        [daemon]
+        app_url = "https://example.com/token"
+        time_to_sleep = 5
+    "#;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        assert_eq!(config.workbook.path, "reg.xlsx");
+        assert_eq!(config.workbook.max_rows, 100);
+        assert_eq!(
+            config.workbook.how_far_in_past_days,
+            DEFAULT_HOW_FAR_IN_PAST_DAYS
+        );
+        assert_eq!(
+            config.workbook.schema.field("Номер_извещения"),
+            Some(Field::RegistryNumber)
+        );
+        assert_eq!(config.daemon.time_to_sleep, 5);
+        assert_eq!(config.daemon.temp_file_path, DEFAULT_TEMP_FILE_PATH);
+        assert_eq!(config.daemon.retry_threshold, DEFAULT_RETRY_THRESHOLD);
+        assert_eq!(config.workbook.date_system, None);
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str("[workbook]\ndate_system = \"1904\"").unwrap();
+        assert_eq!(config.workbook.date_system, Some(DateSystem::Excel1904));
+        assert!(toml::from_str::<Config>("[workbook]\ndate_system = \"1905\"").is_err());
+
+        let config: Config = toml::from_str("[workbook]\nduplicates = \"reject\"").unwrap();
+        assert_eq!(config.workbook.duplicates, Duplicates::Reject);
+        assert_eq!(Config::default().workbook.duplicates, Duplicates::FirstWins);
+    }
+
+    #[test]
+    fn test_timezone() {
+        let config = Config::default();
+        assert_eq!(config.workbook.offset("Москва"), Offset::MSK);
+
+        let config: Config = toml::from_str(
+            r#"
+            [workbook]
+            timezone = "UTC+3"
+            [workbook.region_timezones]
+            "Новосибирская область" = "МСК+4"
+            "Калининград" = "+02:00"
+        "#,
+        )
+        .unwrap();
+        let offset = |region: &str| config.workbook.offset(region).to_string();
+        assert_eq!(offset("новосибирская  область "), "+07:00");
+        assert_eq!(offset("Калининград"), "+02:00");
+        assert_eq!(offset("Тверь"), "+03:00");
+
+        assert!(toml::from_str::<Config>("[workbook]\ntimezone = \"Europe/Moscow\"").is_err());
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        assert!(toml::from_str::<Config>("[daemon]\nsleep = 5").is_err());
+    }
+
+    #[test]
+    fn test_override_from() {
+        let mut config: Config = toml::from_str(CONFIG).unwrap();
+        config
+            .override_from(|k| match k {
+                ENV_WORKBOOK_PATH => Some("other.xlsx".to_owned()),
+                ENV_TIME_TO_SLEEP => Some("60".to_owned()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(config.workbook.path, "other.xlsx");
+        assert_eq!(config.daemon.time_to_sleep, 60);
+        assert_eq!(config.daemon.app_url, "https://example.com/token");
+
+        let res = config.override_from(|k| match k {
+            ENV_RETRY_THRESHOLD => Some("many".to_owned()),
+            _ => None,
+        });
+        assert!(matches!(
+            res,
+            Err(ConfigError::InvalidValueError(ENV_RETRY_THRESHOLD, _))
+        ));
+    }
+
+    #[test]
+    fn test_targets() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let targets = config.targets().unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].name, DEFAULT_TARGET_NAME);
+        assert_eq!(targets[0].workbook.path, "reg.xlsx");
+
+        let mut config: Config = toml::from_str(&format!(
+            "{}{}",
+            CONFIG,
+            r#"
+            [[watch]]
+            name = "first"
+            path = "first.xlsx"
+            time_to_sleep = 60
+
+            [[watch]]
+            name = "second"
+            path = "second.xlsx"
+            app_url = "https://example.com/other"
+            temp_file_path = "second.json"
+            payload = "array"
+        "#
+        ))
+        .unwrap();
+        let targets = config.targets().unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].workbook.path, "first.xlsx");
+        assert_eq!(targets[0].workbook.max_rows, 100);
+        assert_eq!(targets[0].daemon.app_url, "https://example.com/token");
+        assert_eq!(targets[0].daemon.time_to_sleep, 60);
+        assert_eq!(targets[0].daemon.temp_file_path, "temp_first.json");
+        assert_eq!(targets[1].daemon.app_url, "https://example.com/other");
+        assert_eq!(targets[1].daemon.time_to_sleep, 5);
+        assert_eq!(targets[1].daemon.temp_file_path, "second.json");
+        assert_eq!(targets[0].daemon.payload, Payload::Envelope);
+        assert_eq!(targets[1].daemon.payload, Payload::Array);
+
+        // shared values may be overridden, the ones of the single workbook can't
+        config
+            .override_from(|k| match k {
+                ENV_TIME_TO_SLEEP => Some("10".to_owned()),
+                _ => None,
+            })
+            .unwrap();
+        let targets = config.targets().unwrap();
+        assert_eq!(targets[0].daemon.time_to_sleep, 60);
+        assert_eq!(targets[1].daemon.time_to_sleep, 10);
+        config
+            .override_from(|k| match k {
+                ENV_APP_URL => Some("https://example.com/env".to_owned()),
+                _ => None,
+            })
+            .unwrap();
+        assert!(matches!(
+            config.targets(),
+            Err(ConfigError::InvalidValueError(ENV_APP_URL, _))
+        ));
+
+        let mut config: Config = toml::from_str(CONFIG).unwrap();
+        config
+            .override_from(|k| match k {
+                ENV_WORKBOOK_PATH => Some("other.xlsx".to_owned()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(config.targets().unwrap()[0].workbook.path, "other.xlsx");
+    }
+
+    #[test]
+    fn test_targets_errors() {
+        let config: Config = toml::from_str(
+            r#"
+            [[watch]]
+            name = "first"
+            path = "first.xlsx"
+        "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config.targets(),
+            Err(ConfigError::WatchError(_, e)) if matches!(*e, ConfigError::MissingValueError("daemon.app_url"))
+        ));
+
+        let config: Config = toml::from_str(
+            r#"
+            [daemon]
+            app_url = "https://example.com/token"
+
+            [[watch]]
+            name = "first"
+            path = "first.xlsx"
+
+            [[watch]]
+            name = "first"
+            path = "second.xlsx"
+        "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config.targets(),
+            Err(ConfigError::InvalidValueError("watch.name", _))
+        ));
+
+        let config: Config = toml::from_str(
+            r#"
+            [daemon]
+            app_url = "https://example.com/token"
+
+            [[watch]]
+            name = "first"
+            path = "first.xlsx"
+            temp_file_path = "temp.json"
+
+            [[watch]]
+            name = "second"
+            path = "second.xlsx"
+            temp_file_path = "temp.json"
+        "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config.targets(),
+            Err(ConfigError::InvalidValueError("watch.temp_file_path", _))
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = Config::default();
+        assert!(config.validate().is_ok());
+        assert!(matches!(
+            config.require_workbook(),
+            Err(ConfigError::MissingValueError("workbook.path"))
+        ));
+        assert!(matches!(
+            config.require_app_url(),
+            Err(ConfigError::MissingValueError("daemon.app_url"))
+        ));
+
+        let mut config: Config = toml::from_str(CONFIG).unwrap();
+        config.daemon.app_url = "example.com".to_owned();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidValueError("daemon.app_url", _))
+        ));
+
+        let mut config: Config = toml::from_str(CONFIG).unwrap();
+        config.daemon.report_url = "ftp://example.com/report".to_owned();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidValueError("daemon.report_url", _))
+        ));
+
+        let mut config: Config = toml::from_str(CONFIG).unwrap();
+        config.daemon.time_to_sleep = 0;
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidValueError("daemon.time_to_sleep", _))
+        ));
+
+        let mut config: Config = toml::from_str(CONFIG).unwrap();
+        config.workbook.csv.encoding = "koi9".to_owned();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidValueError("workbook.csv", _))
+        ));
+
+        let config: Config =
+            toml::from_str("[workbook.extra]\nnumber = { names = [\"Номер\"] }").unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidValueError("workbook.extra", _))
+        ));
+    }
+}
@@ -1,6 +1,11 @@
-use crate::{excel, simple_time};
-use ctrlc;
-use log::{error, info};
+use crate::{
+    config::{ConfigError, Target},
+    excel::{self, Purchase},
+    payload::{self, Envelope, Kind, Payload},
+    report::{self, CellIssue},
+    simple_time, source,
+};
+use log::{error, info, warn};
 use reqwest::{blocking::Client, header::CONTENT_TYPE};
 use std::{
     fmt, fs,
@@ -12,29 +17,22 @@ use std::{
     time::{self, SystemTime},
 };
 
-/// Daemon sleep interval in seconds
-const TIME_TO_SLEEP: u64 = 30;
-
-const TEMP_FILE_PATH: &str = "temp.json";
-
-const RETRY_THRESHOLD: i32 = 20;
-
 /// Returns last modification time of a file or [std::io::Error]
 fn last_modified_time(path: &Path) -> std::io::Result<SystemTime> {
     fs::metadata(path).and_then(|m| m.modified())
 }
 
 /// Reads content of the file to string
-fn file_content(path: &Path) -> std::io::Result<String> {
-    let mut file = File::open(&path)?;
+pub fn file_content(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
     let mut s = String::new();
     file.read_to_string(&mut s)?;
     Ok(s)
 }
 
 /// Writes string to file
-fn write_to_file(path: &Path, s: String) -> std::io::Result<()> {
-    let mut file = File::create(&path)?;
+pub fn write_to_file(path: &Path, s: String) -> std::io::Result<()> {
+    let mut file = File::create(path)?;
     file.write_all(s.as_bytes())?;
     Ok(())
 }
@@ -48,7 +46,7 @@ fn rm_file(temp_path: &Path) -> std::io::Result<()> {
 }
 
 /// Sends json post request to remote app url
-fn send(client: &Client, url: &str, json: String) -> Result<(), reqwest::Error> {
+pub fn send(client: &Client, url: &str, json: String) -> Result<(), reqwest::Error> {
     let res = client
         .post(url)
         .header(CONTENT_TYPE, "application/json")
@@ -69,7 +67,7 @@ fn send(client: &Client, url: &str, json: String) -> Result<(), reqwest::Error>
 
 fn print_time(sys_time: SystemTime) {
     match simple_time::Moment::from_sys_time(sys_time) {
-        Some(m) => info!("last modification time is: {}", m.to_string()),
+        Some(m) => info!("last modification time is: {}", m),
         None => info!("couldn't parse time from system time"),
     };
 }
@@ -84,37 +82,78 @@ fn interrupt_handler(sig_flag: Arc<Mutex<bool>>) -> Result<(), DaemonError> {
     Ok(())
 }
 
-/// Checks a file for changes every time that is specified by [TIME_TO_SLEEP].
+/// Starts watching of every target in it's own thread
+/// and waits until all of them are finished.
+/// An error in one of the targets is logged and doesn't affect the others
+pub fn watch(targets: Vec<Target>) -> Result<(), DaemonError> {
+    let client = Client::new();
+
+    // Ctrl+C handling
+    let interrupt_sig_handler = Arc::new(Mutex::new(false));
+    interrupt_handler(interrupt_sig_handler.clone())?;
+
+    let mut handles = Vec::with_capacity(targets.len());
+    for target in targets {
+        let client = client.clone();
+        let interrupt_sig = interrupt_sig_handler.clone();
+        // thread name is used by logger as prefix
+        let handle = thread::Builder::new()
+            .name(target.name.clone())
+            .spawn(move || {
+                if let Err(e) = watch_target(&target, &client, interrupt_sig) {
+                    error!("stopped watching: {}", &e);
+                }
+            })?;
+        handles.push(handle);
+    }
+
+    for handle in handles {
+        if handle.join().is_err() {
+            error!("watching thread panicked");
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks a file for changes every time that is specified by `time_to_sleep`.
 /// This daemon has it's own litlle presistent store which is a file with
 /// result of previous checking. So if the change of the file is detected,
 /// than it either compare previous result with the new one or just take new one
-/// and send it to the remote app url (which is where database resides)
-pub fn watch(file_path: &str, to_send_url: &str) -> Result<(), DaemonError> {
+/// and send it to the remote app url (which is where database resides).
+/// The store keeps the sequence number of the last sent payload too
+fn watch_target(
+    target: &Target,
+    client: &Client,
+    interrupt_sig: Arc<Mutex<bool>>,
+) -> Result<(), DaemonError> {
+    let file_path = &target.workbook.path;
+    let to_send_url = &target.daemon.app_url;
     let path = Path::new(file_path);
-    let temp_path = Path::new(TEMP_FILE_PATH); // path of the storage file
-
-    let sleep_time = time::Duration::from_secs(TIME_TO_SLEEP);
+    let temp_path = Path::new(&target.daemon.temp_file_path); // path of the storage file
+    let workbook = payload::Workbook {
+        name: target.name.clone(),
+        path: file_path.clone(),
+    };
 
-    let mut last_mod_time = last_modified_time(&path)?;
+    let sleep_time = time::Duration::from_secs(target.daemon.time_to_sleep);
 
-    let client = Client::new();
+    let source = source::open(&target.workbook);
 
-    // Ctrl+C handling
-    let interrupt_sig_handler = Arc::new(Mutex::new(false));
-    let interrupt_sig_main = interrupt_sig_handler.clone();
-    interrupt_handler(interrupt_sig_handler)?;
+    let mut last_mod_time = last_modified_time(path)?;
 
-    info!("start watching to '{}'", &file_path);
+    info!("start watching to '{}'", file_path);
 
     let mut retries = 0;
+    let mut last_report: Vec<CellIssue> = Vec::new();
 
-    while *interrupt_sig_main.lock().unwrap() != true {
-        if retries >= RETRY_THRESHOLD {
+    while !*interrupt_sig.lock().unwrap() {
+        if retries >= target.daemon.retry_threshold {
             break;
         }
         thread::sleep(sleep_time);
 
-        let time_checked = match last_modified_time(&path) {
+        let time_checked = match last_modified_time(path) {
             Ok(t) => {
                 retries = 0;
                 t
@@ -132,13 +171,20 @@ pub fn watch(file_path: &str, to_send_url: &str) -> Result<(), DaemonError> {
         info!("file change detected");
 
         // we get active state records from the file
-        let new_snapshot = match excel::active_state(&path) {
-            Ok(Some(s)) => {
+        let (new_snapshot, issues) = match source.active_state_checked() {
+            Ok(r) => {
                 retries = 0;
-                s
+                r
             }
-            Ok(None) => {
+            // broken schema or duplicates are not going to be fixed by retrying,
+            // so we don't send anything and wait for the next change
+            Err(
+                e @ excel::WorkbookError::SchemaError(_)
+                | e @ excel::WorkbookError::DuplicateError(_),
+            ) => {
+                error!("refused to send snapshot: {}", &e);
                 retries = 0;
+                last_mod_time = time_checked;
                 continue;
             }
             Err(e) => {
@@ -148,31 +194,68 @@ pub fn watch(file_path: &str, to_send_url: &str) -> Result<(), DaemonError> {
             }
         };
 
+        if !issues.is_empty() {
+            warn!("{} cells of active records can't be read", issues.len());
+        }
+        // the report is sent only when it's changed
+        // and separately from the records
+        let report_url = &target.daemon.report_url;
+        if !report_url.is_empty() && issues != last_report {
+            let json = report::to_json(&issues).map_err(excel::WorkbookError::from)?;
+            if send(client, report_url, json).is_ok() {
+                last_report = issues;
+            }
+        }
+
+        let new_snapshot = match new_snapshot {
+            Some(s) => s,
+            None => continue,
+        };
+
         // if we have a temp file with previous records
         // than we compare old with new and gets result set
-        let json = if temp_path.exists() {
-            let old_snapshot = file_content(temp_path)?;
-            match excel::active_state_json_compared(&old_snapshot, &new_snapshot) {
-                Ok(Some(s)) => s,
-                Ok(None) => {
+        let (payload, sequence) = if temp_path.exists() {
+            let old_snapshot: Envelope<Purchase> = Envelope::from_json(&file_content(temp_path)?)
+                .map_err(excel::WorkbookError::from)?;
+            let sequence = old_snapshot.sequence + 1;
+            match excel::active_state_compared(
+                old_snapshot.records,
+                &new_snapshot,
+                &target.workbook,
+            ) {
+                Some(changes) => (
+                    Envelope::new(workbook.clone(), sequence, Kind::Changes, changes)
+                        .to_json(target.daemon.payload),
+                    sequence,
+                ),
+                None => {
                     info!("no changes in records");
                     last_mod_time = time_checked;
                     print_time(last_mod_time);
                     continue;
                 }
-                Err(e) => return Err(e.into()),
             }
         } else {
-            excel::to_json(&new_snapshot)? // or we just take new records
+            // or we just take new records
+            let records: Vec<&Purchase> = new_snapshot.iter().collect();
+            (
+                Envelope::new(workbook.clone(), 1, Kind::Snapshot, records)
+                    .to_json(target.daemon.payload),
+                1,
+            )
         };
+        let json = payload.map_err(excel::WorkbookError::from)?;
 
         // if we have an error from remote database
         // than error is logged by send function
-        if let Err(_) = send(&client, to_send_url, json) {
+        if send(client, to_send_url, json).is_err() {
             continue;
         }
 
-        let new_snapshot = excel::to_json(&new_snapshot)?;
+        // the store is always an envelope to keep the sequence
+        let new_snapshot = Envelope::new(workbook.clone(), sequence, Kind::Snapshot, new_snapshot)
+            .to_json(Payload::Envelope)
+            .map_err(excel::WorkbookError::from)?;
 
         write_to_file(temp_path, new_snapshot)?;
         last_mod_time = time_checked;
@@ -186,12 +269,28 @@ pub fn watch(file_path: &str, to_send_url: &str) -> Result<(), DaemonError> {
 }
 
 /// DaemonError is the wrapper around
-/// either [std::io::Error] or [excel::WorkbookError]
+/// [std::io::Error], [excel::WorkbookError], [ConfigError]
+/// or [reqwest::Error]
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum DaemonError {
     SetSignalError,
     IoError(std::io::Error),
     WorkBookError(excel::WorkbookError),
+    ConfigError(ConfigError),
+    RequestError(reqwest::Error),
+}
+
+impl From<ConfigError> for DaemonError {
+    fn from(error: ConfigError) -> Self {
+        DaemonError::ConfigError(error)
+    }
+}
+
+impl From<reqwest::Error> for DaemonError {
+    fn from(error: reqwest::Error) -> Self {
+        DaemonError::RequestError(error)
+    }
 }
 
 impl From<std::io::Error> for DaemonError {
@@ -209,9 +308,11 @@ impl From<excel::WorkbookError> for DaemonError {
 impl fmt::Display for DaemonError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            DaemonError::WorkBookError(e) => write!(f, "{:?}", &e),
+            DaemonError::WorkBookError(e) => write!(f, "{}", &e),
             DaemonError::IoError(e) => write!(f, "{:?}", &e),
             DaemonError::SetSignalError => write!(f, "error setting Ctrl-C handler"),
+            DaemonError::ConfigError(e) => write!(f, "{}", &e),
+            DaemonError::RequestError(e) => write!(f, "{:?}", &e),
         }
     }
 }
@@ -1,69 +1,78 @@
-use crate::simple_time::Moment;
-use calamine::{open_workbook, DataType, Range, Reader, Xlsx, XlsxError};
+use crate::{
+    config::WorkbookConfig,
+    money::Money,
+    participants::{self, Participant},
+    report::{CellIssue, Problem},
+    schema::{Columns, Duplicates, Extras, Field, Schema},
+    simple_time::{self, Moment, Offset},
+};
+use calamine::{open_workbook_auto, DataType, Range, Reader, Sheets};
+use log::warn;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 #[cfg(test)]
 use std::env;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt,
     fs::File,
-    io::BufReader,
+    hash::Hash,
+    io::Read,
     path::Path,
-    time::{Duration, SystemTime},
+    time::SystemTime,
 };
 
-const PURCHASE_SUBJECT_ABBR: &str = "Предмет";
-const PURCHASE_SUBJECT: &str = "Поставляемые_товары";
-const DATE_COLLECTING_BIDS: &str = "Дата_окончания_подачи_заявок";
-const TIME_COLLECTING_BIDS: &str = "Время_окончания_подачи_заявок";
-const DATE_APPROVAL: &str = "Дата_окончания_срока_рассмотрения_заявок";
-const DATE_BIDDING: &str = "Дата_проведения_аукциона_конкурса";
-const TIME_BIDDING: &str = "Время_проведения_аукциона_конкурса";
-const REGION: &str = "Регион";
-const CUSTOMER_TYPE: &str = "Заказчик";
-const MAX_PRICE: &str = "НМЦК";
-const APPLICATION_GUARANTEE: &str = "Размер_обеспечения_заявки";
-const CONTRACT_GUARANTEE: &str = "Размер_обеспечения_контракта";
-const STATUS: &str = "Статус";
-const OUR_PARTICIPANTS: &str = "Наши_участники";
-const ESTIMATION: &str = "Расчет";
-const NUMBER: &str = "Номер";
-const PURCHASE_TYPE: &str = "Форма_проведения";
-const ETP: &str = "Площадка";
-const WINNER: &str = "Победитель";
-const WINNER_PRICE: &str = "Сумма_выигранного_лота";
-const PARTICIPANTS: &str = "Участники";
-const EMPTY: &str = "";
-const STATUS_GO: &str = "идем";
-const STATUS_NOT_GO: &str = "не идем";
-const STATUS_ADMITTED: &str = "допущены";
-const STATUS_APPLY: &str = "заявлены";
-const STATUS_WIN: &str = "выиграли";
-const STATUS_LOSS: &str = "не выиграли";
-const STATUS_ESTIMATION: &str = "расчет";
 const NAMED_RANGES_COUNT: usize = 20;
 const RADIX: u32 = 36;
 
 const TOTAL_COLUMNS: u32 = 16384;
-// we don't expect that workbook
-// has a number of active rows greater than that
-const WORKBOOK_MAX_ROWS: usize = 7000;
-// maximum days that we want to look in past
-// searching for rows
-const HOW_FAR_IN_PAST_DAYS: u64 = 16;
 
 // 01.01.1970 in excel representation
 const EXCEL_UNIX_EPOCH: u64 = 25569;
+// excel keeps only 15 significant digits of a number,
+// so the greater ones are rounded
+const EXCEL_MAX_EXACT_NUMBER: f64 = 999_999_999_999_999.0;
+// days between 1900 and 1904 date systems
+const EXCEL_1904_OFFSET: f64 = 1462.0;
+// record of xlsb workbook properties and it's flag of 1904 date system
+const XLSB_WB_PROP: u32 = 0x0099;
+const XLSB_1904_FLAG: u32 = 0x01;
+// xls workbook is a compound file, the first sectors ids above that are special
+const CFB_SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+const CFB_MAX_SECTOR: u32 = 0xFFFF_FFFA;
+const CFB_MINI_SECTOR_SIZE: usize = 64;
+const XLS_DATEMODE: u16 = 0x0022;
+const XLS_EOF: u16 = 0x000A;
 
 /// Alias result type for this module
 type ExcelResult<T> = std::result::Result<T, WorkbookError>;
 
+/// Active state records if any and the problem cells found while reading them
+pub type CheckedRecords = (Option<Vec<Purchase>>, Vec<CellIssue>);
+
 // WorkbookError is error type for this module
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum WorkbookError {
     InvalidColumnNameError(String),
-    XlsxError(XlsxError),
+    SpreadsheetError(calamine::Error),
     JsonSerializeError(serde_json::Error),
+    SchemaError(Vec<ColumnsCheck>),
+    DuplicateError(Vec<Duplicate>),
+    IoError(std::io::Error),
+    CsvError(csv::Error),
+}
+
+impl From<std::io::Error> for WorkbookError {
+    fn from(error: std::io::Error) -> Self {
+        WorkbookError::IoError(error)
+    }
+}
+
+impl From<csv::Error> for WorkbookError {
+    fn from(error: csv::Error) -> Self {
+        WorkbookError::CsvError(error)
+    }
 }
 
 impl From<serde_json::Error> for WorkbookError {
@@ -76,20 +85,31 @@ impl fmt::Display for WorkbookError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             WorkbookError::InvalidColumnNameError(s) => write!(f, "invalid column name: {:?}", &s),
-            WorkbookError::XlsxError(e) => write!(f, "{:?}", &e),
+            WorkbookError::SpreadsheetError(e) => write!(f, "{}", &e),
             WorkbookError::JsonSerializeError(e) => write!(f, "cannot serialize struct {:?}", e),
+            WorkbookError::SchemaError(checks) => {
+                let checks: Vec<String> = checks.iter().map(|c| c.to_string()).collect();
+                write!(f, "workbook doesn't match schema: {}", checks.join("; "))
+            }
+            WorkbookError::DuplicateError(duplicates) => {
+                let duplicates: Vec<String> = duplicates.iter().map(|d| d.to_string()).collect();
+                write!(f, "workbook has duplicates: {}", duplicates.join("; "))
+            }
+            WorkbookError::IoError(e) => write!(f, "{}", &e),
+            WorkbookError::CsvError(e) => write!(f, "cannot read csv: {}", &e),
         }
     }
 }
 
 /// Prints to console active state records as json.
-/// The 'activeness' of state is determined by [is_active state]
+/// The 'activeness' of state is determined by [Statuses::active]
 /// function
 #[cfg(test)]
-pub fn print_active_state_json(wb_path: &Path) -> ExcelResult<()> {
+pub fn print_active_state_json(wb_path: &Path, config: &WorkbookConfig) -> ExcelResult<()> {
     let mut workbook = open_wb(wb_path)?;
+    let dates = date_system(wb_path, config);
 
-    if let Some(purches) = active_purchases(&mut workbook) {
+    if let Some(purches) = active_purchases(&mut workbook, dates, config, &mut Vec::new())? {
         println!("{}", purches.len());
         for p in purches {
             let j = serde_json::to_string_pretty(&p)?;
@@ -100,66 +120,88 @@ pub fn print_active_state_json(wb_path: &Path) -> ExcelResult<()> {
 }
 
 /// Returns a json string of active state records.
-/// The 'activeness' of state is determined by [is_active state]
+/// The 'activeness' of state is determined by [Statuses::active]
 /// function
 #[cfg(test)]
-fn active_state_json(wb_path: &Path) -> ExcelResult<Option<String>> {
+fn active_state_json(wb_path: &Path, config: &WorkbookConfig) -> ExcelResult<Option<String>> {
     let mut workbook = open_wb(wb_path)?;
+    let dates = date_system(wb_path, config);
 
-    if let Some(purches) = active_purchases(&mut workbook) {
+    if let Some(purches) = active_purchases(&mut workbook, dates, config, &mut Vec::new())? {
         Ok(Some(serde_json::to_string(&purches)?))
     } else {
         Ok(None)
     }
 }
 
-/// Converts vector of [Purchase] to json string
-pub fn to_json(purches: &Vec<Purchase>) -> ExcelResult<String> {
-    Ok(serde_json::to_string(purches)?)
-}
-
 /// Returns a result of comparing two sets of [Purchase]'s.
-/// The first one is expected to be a [str] from some file
+/// The first one is expected to be read from some file
 /// and considered as 'old'. The second one is expected to be
 /// result of [active_state] function and considered as 'new'.
 /// The goal is to get the records that has changed or simply new.
-pub fn active_state_json_compared(
-    old_purches: &str,
-    new_purches: &Vec<Purchase>,
-) -> ExcelResult<Option<String>> {
-    // first we deserialize first set to a vector
-    let old_purches: Vec<Purchase> = serde_json::from_str(old_purches)?;
-
-    // second we build a hash set from that vector
-    let mut old_purches_map: HashMap<String, Purchase> = HashMap::with_capacity(old_purches.len());
+/// Extra columns are compared if it's set by config. Records without
+/// the registry number can't be matched, so they are left out
+pub fn active_state_compared(
+    old_purches: Vec<Purchase>,
+    new_purches: &[Purchase],
+    config: &WorkbookConfig,
+) -> Option<Vec<Purchase>> {
+    // first we build a hash set from the old set,
+    // old snapshots may still have records without the number
+    let mut old_purches_map: HashMap<(String, String), Purchase> =
+        HashMap::with_capacity(old_purches.len());
     for p in old_purches.into_iter() {
-        old_purches_map.insert(p.registry_number.clone(), p); // this key is unique
+        if !p.registry_number.is_empty() {
+            old_purches_map.insert(p.key(), p); // this key is unique
+        }
     }
-
-    let result = changed(&mut old_purches_map, new_purches);
-    if result.len() == 0 {
-        return Ok(None);
+    let new_purches: Vec<Purchase> = new_purches
+        .iter()
+        .filter(|p| !p.registry_number.is_empty())
+        .cloned()
+        .collect();
+
+    let result = changed(
+        old_purches_map,
+        &new_purches,
+        &config.statuses.removed,
+        &config.extra,
+    );
+    if result.is_empty() {
+        return None;
     }
-    Ok(Some(serde_json::to_string(&result)?))
+    Some(result)
 }
 
 /// Compares two sets of data and returns resulting set
-/// of records that has changed or records that is new
-fn changed<'a>(
-    old: &'a mut HashMap<String, Purchase>,
-    new: &'a Vec<Purchase>,
-) -> Vec<&'a Purchase> {
-    let mut result: Vec<&Purchase> = Vec::new();
+/// of records that has changed or records that is new.
+/// Changed records have the list of their changed fields
+fn changed(
+    mut old: HashMap<(String, String), Purchase>,
+    new: &[Purchase],
+    removed_status: &str,
+    extras: &Extras,
+) -> Vec<Purchase> {
+    let mut result: Vec<Purchase> = Vec::new();
 
     for p in new {
         // if we have match on entries
         // we remove one from the first one
-        match old.remove(&p.registry_number) {
-            // than we compare if they are equal
-            Some(v) if v == *p => continue, // if so we pass on next
-            // if they are not equal or we didn't find
-            // match than we push it to result
-            _ => result.push(p),
+        match old.remove(&p.key()) {
+            // than we compare their fields
+            Some(v) => {
+                let changes = v.field_changes(p, extras);
+                // if none of them is changed we pass on next
+                if changes.is_empty() {
+                    continue;
+                }
+                result.push(Purchase {
+                    changes,
+                    ..p.clone()
+                });
+            }
+            // if we didn't find match than it's new
+            None => result.push(p.clone()),
         }
     }
 
@@ -168,7 +210,12 @@ fn changed<'a>(
     // from active state and we need to include them
     // to the result with inactive state
     for (_, mut p) in old.into_iter() {
-        p.status = STATUS_NOT_GO.to_string();
+        p.changes = vec![FieldChange::new(
+            Field::Status.as_str(),
+            Value::from(p.status.as_str()),
+            Value::from(removed_status),
+        )];
+        p.status = removed_status.to_owned();
         result.push(p);
     }
 
@@ -176,13 +223,14 @@ fn changed<'a>(
 }
 
 /// Prints to console active state records.
-/// The 'activeness' of state is determined by [is_active state]
+/// The 'activeness' of state is determined by [Statuses::active]
 /// function
 #[cfg(test)]
-pub fn print_active_state(wb_path: &Path) -> ExcelResult<()> {
+pub fn print_active_state(wb_path: &Path, config: &WorkbookConfig) -> ExcelResult<()> {
     let mut workbook = open_wb(wb_path)?;
+    let dates = date_system(wb_path, config);
 
-    if let Some(purches) = active_purchases(&mut workbook) {
+    if let Some(purches) = active_purchases(&mut workbook, dates, config, &mut Vec::new())? {
         for p in purches {
             println!(
                 "rn:{:?} ps:{:?} reg:{:?} ct:{:?} status:{:?} est:{:?} bid_datetime:{:?}",
@@ -201,498 +249,3020 @@ pub fn print_active_state(wb_path: &Path) -> ExcelResult<()> {
 }
 
 /// Returns a vector of active state records.
-/// The 'activeness' of state is determined by [is_active state]
+/// The 'activeness' of state is determined by [Statuses::active]
 /// function
-pub fn active_state(wb_path: &Path) -> ExcelResult<Option<Vec<Purchase>>> {
+pub fn active_state(wb_path: &Path, config: &WorkbookConfig) -> ExcelResult<Option<Vec<Purchase>>> {
     let mut workbook = open_wb(wb_path)?;
+    let dates = date_system(wb_path, config);
 
-    Ok(active_purchases(&mut workbook))
+    active_purchases(&mut workbook, dates, config, &mut Vec::new())
 }
 
-/// Opens an excel workbook for further processing
-fn open_wb(wb_path: &Path) -> ExcelResult<Xlsx<BufReader<File>>> {
-    // let path = Path::new(path);
-    Ok(open_workbook(wb_path).map_err(|x| WorkbookError::XlsxError(x))?)
+/// Returns a vector of active state records with
+/// the report of the cells which values can't be read
+pub fn active_state_checked(
+    wb_path: &Path,
+    config: &WorkbookConfig,
+) -> ExcelResult<CheckedRecords> {
+    let mut workbook = open_wb(wb_path)?;
+    let dates = date_system(wb_path, config);
+
+    let mut issues = Vec::new();
+    let purches = active_purchases(&mut workbook, dates, config, &mut issues)?;
+    Ok((purches, issues))
 }
 
-/// Returns a vector of active state [Purchase]'s if any
-fn active_purchases(workbook: &mut Xlsx<BufReader<File>>) -> Option<Vec<Purchase>> {
-    // this function heavily relias on named ranges in workbook
-    // and expected that they are equal to those that
-    // processed by [is_expectable] function
-    let named_ranges = named_ranges(&workbook);
+/// Result of checking workbook columns against expected ones
+#[derive(Debug, Default)]
+pub struct ColumnsCheck {
+    pub sheet: String, // empty if the source has no sheets e.g. csv
+    pub missing: Vec<Field>,
+    pub invalid: Vec<(Field, String)>, // field and it's named range
+}
 
-    let named_cols = named_cols(&named_ranges);
+impl ColumnsCheck {
+    /// Checks located columns and named ranges of the sheet against all the fields
+    fn new(
+        sheet: &str,
+        named_ranges: &[NamedRange],
+        field_cols: &HashMap<Field, Location>,
+        config: &WorkbookConfig,
+    ) -> Self {
+        let mut check = ColumnsCheck {
+            sheet: sheet.to_owned(),
+            ..ColumnsCheck::default()
+        };
 
-    match workbook.worksheet_range(&named_ranges[0].sheet) {
-        Some(Ok(range)) => {
-            let purches = active_state_cells(range, named_cols);
-            if !purches.is_empty() {
-                Some(purches)
-            } else {
-                None
+        if config.columns != Columns::Headers {
+            for nr in named_ranges.iter() {
+                if nr.location().is_err() {
+                    check
+                        .invalid
+                        .push((nr.field, format!("{}!{}", nr.sheet, nr.range)));
+                }
             }
         }
-        _ => None,
-    }
-}
 
-enum ColumnPosition {
-    Left = 0,
-    #[allow(dead_code)]
-    Right = 1,
-}
+        for field in Field::ALL.iter().filter(|f| f.is_expected()) {
+            // malformed named range of the field is already reported
+            if !field_cols.contains_key(field) && !check.invalid.iter().any(|(f, _)| f == field) {
+                check.missing.push(*field);
+            }
+        }
 
-#[derive(Default, Debug)]
-struct NamedCols {
-    registry_number: usize,
-    purchase_subject: usize,
-    purchase_abbr: usize,
-    purchase_type: usize,
-    collecting_datetime: usize,
-    approval_datetime: usize,
-    bidding_datetime: usize,
-    bidding_date: usize,
-    collecting_date: usize,
-    region: usize,
-    customer_type: usize,
-    max_price: usize,
-    application_guarantee: usize,
-    contract_guarantee: usize,
-    status: usize,
-    our_participants: usize,
-    estimation: usize,
-    etp: usize,
-    winner: usize,
-    winner_price: usize,
-    participants: usize,
-}
+        check
+    }
 
-/// This type is represent a row in excel workbook
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Purchase {
-    registry_number: String,
-    purchase_subject: String,
-    purchase_abbr: String,
-    purchase_type: String,
-    collecting_datetime: String,
-    approval_datetime: String,
-    bidding_datetime: String,
-    region: String,
-    customer_type: String,
-    max_price: f64,
-    application_guarantee: f64,
-    contract_guarantee: f64,
-    status: String,
-    our_participants: String,
-    estimation: f64,
-    etp: String,
-    winner: String,
-    winner_price: f64,
-    participants: String,
-}
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty()
+    }
 
-// [Purchase] equivalence logic
-impl PartialEq for Purchase {
-    fn eq(&self, other: &Self) -> bool {
-        self.registry_number == other.registry_number
-            && self.collecting_datetime == other.collecting_datetime
-            && self.approval_datetime == other.approval_datetime
-            && self.bidding_datetime == other.bidding_datetime
-            && self.region == other.region
-            && self.status == other.status
-            && self.estimation as i64 == other.estimation as i64
-            && self.our_participants == other.our_participants
-            && self.winner == other.winner
-            && self.winner_price as i64 == other.winner_price as i64
-            && self.participants == other.participants
-    }
-}
-
-struct NamedRange<'a> {
-    name: &'a str,
-    sheet: String, // sheet portion of 'List1!$A$:$A$' == 'List1'
-    range: String, // range portion of 'List1!$A$:$A$' == '$A$:$A$'
+    /// Records can't be trusted if any named range is malformed
+    /// or any of the required fields is missing
+    pub fn is_broken(&self) -> bool {
+        !self.invalid.is_empty() || self.missing.iter().any(Field::is_required)
+    }
 }
 
-impl NamedRange<'_> {
-    pub fn new(def_name: &(String, String)) -> Self {
-        Self {
-            name: Self::match_name(&def_name.0),
-            sheet: Self::parse_sheet(&def_name.1),
-            range: Self::parse_range(&def_name.1),
+impl fmt::Display for ColumnsCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |required: bool| {
+            self.missing
+                .iter()
+                .filter(|field| field.is_required() == required)
+                .map(Field::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut problems = Vec::new();
+        let required = list(true);
+        if !required.is_empty() {
+            problems.push(format!("missing required [{}]", required));
+        }
+        let optional = list(false);
+        if !optional.is_empty() {
+            problems.push(format!("missing optional [{}]", optional));
+        }
+        if !self.invalid.is_empty() {
+            let invalid: Vec<String> = self
+                .invalid
+                .iter()
+                .map(|(field, range)| format!("{} = '{}'", field, range))
+                .collect();
+            problems.push(format!("invalid [{}]", invalid.join(", ")));
         }
-    }
 
-    fn match_name<'a>(s: &str) -> &'a str {
-        match s {
-            DATE_COLLECTING_BIDS => DATE_COLLECTING_BIDS,
-            TIME_COLLECTING_BIDS => TIME_COLLECTING_BIDS,
-            DATE_APPROVAL => DATE_APPROVAL,
-            DATE_BIDDING => DATE_BIDDING,
-            TIME_BIDDING => TIME_BIDDING,
-            MAX_PRICE => MAX_PRICE,
-            NUMBER => NUMBER,
-            WINNER => WINNER,
-            STATUS => STATUS,
-            WINNER_PRICE => WINNER_PRICE,
-            PARTICIPANTS => PARTICIPANTS,
-            PURCHASE_TYPE => PURCHASE_TYPE,
-            PURCHASE_SUBJECT => PURCHASE_SUBJECT,
-            PURCHASE_SUBJECT_ABBR => PURCHASE_SUBJECT_ABBR,
-            OUR_PARTICIPANTS => OUR_PARTICIPANTS,
-            ETP => ETP,
-            APPLICATION_GUARANTEE => APPLICATION_GUARANTEE,
-            CONTRACT_GUARANTEE => CONTRACT_GUARANTEE,
-            CUSTOMER_TYPE => CUSTOMER_TYPE,
-            ESTIMATION => ESTIMATION,
-            REGION => REGION,
-            _ => EMPTY,
+        if !self.sheet.is_empty() {
+            write!(f, "sheet '{}': ", self.sheet)?;
+        }
+        if problems.is_empty() {
+            write!(f, "ok")
+        } else {
+            write!(f, "{}", problems.join("; "))
         }
     }
+}
 
-    /// Gets a sheet portion of name
-    fn parse_sheet(s: &str) -> String {
-        s.split('!').nth(0).unwrap_or_default().to_owned()
+/// Checks that every expected column is located in every read sheet
+/// of the workbook and that every named range points to a valid column
+pub fn check_columns(wb_path: &Path, config: &WorkbookConfig) -> ExcelResult<Vec<ColumnsCheck>> {
+    let mut workbook = open_wb(wb_path)?;
+    let named_ranges = named_ranges(&workbook, &config.schema);
+
+    let checks: Vec<ColumnsCheck> = sheet_ranges(&mut workbook, &named_ranges, config)
+        .into_iter()
+        .map(|s| s.check)
+        .collect();
+    if checks.is_empty() {
+        return Ok(vec![no_sheets_check(config)]);
     }
 
-    /// Gets a range portion of name
-    fn parse_range(s: &str) -> String {
-        s.split('!').nth(1).unwrap_or_default().to_owned()
+    Ok(checks)
+}
+
+/// Opens a workbook for further processing. Format of the workbook
+/// (xlsx, xlsm, xlsb, xls or ods) is detected by extension
+/// or if it's unknown by trying every format in turn
+fn open_wb(wb_path: &Path) -> ExcelResult<Sheets> {
+    open_workbook_auto(wb_path).map_err(WorkbookError::SpreadsheetError)
+}
+
+/// Excel date with the offset that is written along with it e.g. '10:00 МСК'
+type ZonedDate = (f64, Option<Offset>);
+
+/// Date system of the workbook i.e. the day that serial 1 stands for
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum DateSystem {
+    // 01.01.1900 with non-existent 29.02.1900
+    #[default]
+    #[serde(rename = "1900")]
+    Excel1900,
+    // 02.01.1904, default of the old excel for mac
+    #[serde(rename = "1904")]
+    Excel1904,
+}
+
+impl DateSystem {
+    /// Converts serial of the workbook to the continuous count of days
+    /// since 30.12.1899 that the rest of this module works with.
+    /// Values less than 1.0 are kept as is, because they are just times
+    fn normalize(self, serial: f64) -> f64 {
+        match self {
+            _ if serial < 1.0 => serial,
+            // excel thinks that 1900 is a leap year, so the days
+            // before 29.02.1900 are shifted by one
+            DateSystem::Excel1900 if serial < 60.0 => serial + 1.0,
+            DateSystem::Excel1900 => serial,
+            DateSystem::Excel1904 => serial + EXCEL_1904_OFFSET,
+        }
     }
+}
 
-    /// Returns column char name
-    pub fn col_name(&self, cp: ColumnPosition) -> String {
-        self.range
-            .split(":")
-            .nth(cp as usize)
-            .unwrap_or_default()
-            .replace("$", "")
+/// Returns date system that is set by config or is detected by the workbook
+/// properties of xlsx, xlsb and xls. Ods keeps dates as text, so it doesn't matter
+fn date_system(wb_path: &Path, config: &WorkbookConfig) -> DateSystem {
+    if let Some(dates) = config.date_system {
+        return dates;
     }
 
-    /// Maps column char name to column serial number e.g. 'A' == 1
-    pub fn col_num(&self, cp: ColumnPosition) -> ExcelResult<usize> {
-        let col_name = self.col_name(cp);
+    // not a zip archive e.g. xls or something that calamine will complain about
+    let mut archive = match File::open(wb_path).map(zip::ZipArchive::new) {
+        Ok(Ok(a)) => a,
+        _ => return xls_date_system(wb_path),
+    };
+
+    let mut read = |name: &str| -> Option<Vec<u8>> {
+        let mut buf = Vec::new();
+        archive.by_name(name).ok()?.read_to_end(&mut buf).ok()?;
+        Some(buf)
+    };
+
+    let is_1904 = if let Some(xml) = read("xl/workbook.xml") {
+        xlsx_is_1904(&String::from_utf8_lossy(&xml))
+    } else if let Some(bin) = read("xl/workbook.bin") {
+        xlsb_is_1904(&bin)
+    } else {
+        false
+    };
 
-        if col_name.len() == 0 {
-            return Err(WorkbookError::InvalidColumnNameError(col_name));
-        }
+    if is_1904 {
+        DateSystem::Excel1904
+    } else {
+        DateSystem::Excel1900
+    }
+}
 
-        let mut col = 0;
-        let mut multi = 1;
+/// Checks 'date1904' attribute of the 'workbookPr' element of xlsx workbook part
+fn xlsx_is_1904(xml: &str) -> bool {
+    let pr = match xml.find("workbookPr") {
+        Some(i) => &xml[i..],
+        None => return false,
+    };
+    let pr = &pr[..pr.find('>').unwrap_or(pr.len())];
+    pr.contains("date1904=\"1\"") || pr.contains("date1904=\"true\"")
+}
 
-        for c in col_name.chars().rev() {
-            match c {
-                'A'..='Z' => {
-                    if let (Some(c_num), Some(a_num)) = (c.to_digit(RADIX), 'A'.to_digit(RADIX)) {
-                        col += (c_num - a_num + 1) * multi;
-                    }
-                }
-                'a'..='z' => {
-                    if let (Some(c_num), Some(a_num)) = (c.to_digit(RADIX), 'a'.to_digit(RADIX)) {
-                        col += (c_num - a_num + 1) * multi;
-                    }
-                }
-                _ => return Err(WorkbookError::InvalidColumnNameError(col_name)),
+/// Checks the flag of 1904 date system of xlsb workbook properties record
+fn xlsb_is_1904(buf: &[u8]) -> bool {
+    // record type and size are variable length numbers
+    // where the high bit of the byte means that there is the next one
+    fn var_num(buf: &[u8], pos: &mut usize, max_bytes: usize) -> Option<u32> {
+        let mut num = 0;
+        for i in 0..max_bytes {
+            let b = *buf.get(*pos)?;
+            *pos += 1;
+            num |= ((b & 0x7F) as u32) << (7 * i);
+            if b & 0x80 == 0 {
+                break;
             }
-            multi *= 26;
         }
+        Some(num)
+    }
 
-        if col > TOTAL_COLUMNS {
-            return Err(WorkbookError::InvalidColumnNameError(col_name));
+    let mut pos = 0;
+    while pos < buf.len() {
+        let (typ, size) = match (var_num(buf, &mut pos, 2), var_num(buf, &mut pos, 4)) {
+            (Some(t), Some(s)) => (t, s as usize),
+            _ => return false,
+        };
+        if typ == XLSB_WB_PROP {
+            return match buf.get(pos..pos + 4) {
+                Some(b) => {
+                    let flags = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                    flags & XLSB_1904_FLAG != 0
+                }
+                None => false,
+            };
         }
-
-        Ok(col as usize - 1)
+        pos += size;
     }
+    false
 }
 
-/// Returns a vector of [NamedRange]'s build up from workbook defined names
-fn named_ranges<'a>(workbook: &Xlsx<BufReader<File>>) -> Vec<NamedRange<'a>> {
-    let mut named_ranges: Vec<NamedRange> = Vec::with_capacity(NAMED_RANGES_COUNT);
-
-    for name in workbook
-        .defined_names()
-        .iter()
-        .filter(|(n, _)| is_expectable(n))
-    {
-        named_ranges.push(NamedRange::new(name));
+/// Returns date system of xls workbook or the default one if it's not xls.
+/// Warns if the workbook can't be read, calamine will tell why
+fn xls_date_system(wb_path: &Path) -> DateSystem {
+    let buf = match std::fs::read(wb_path) {
+        Ok(b) if b.starts_with(&CFB_SIGNATURE) => b,
+        _ => return DateSystem::default(),
+    };
+    match xls_is_1904(&buf) {
+        Some(true) => DateSystem::Excel1904,
+        Some(false) => DateSystem::Excel1900,
+        None => {
+            warn!(
+                "can't read date system of '{}', 1900 one is used",
+                wb_path.display()
+            );
+            DateSystem::default()
+        }
     }
-
-    named_ranges
 }
 
-/// Returns [NamedCols] struct that is represent result of
-/// mapping [NamedRange] excel char column to serial number e.g. 'A' == 1
-fn named_cols(named_ranges: &Vec<NamedRange>) -> NamedCols {
-    let mut named_cols = NamedCols::default();
-
-    for nr in named_ranges {
-        let col = nr.col_num(ColumnPosition::Left).unwrap_or_default();
-        match nr.name {
-            DATE_BIDDING => named_cols.bidding_date = col,
-            DATE_COLLECTING_BIDS => named_cols.collecting_date = col,
-            TIME_COLLECTING_BIDS => named_cols.collecting_datetime = col,
-            DATE_APPROVAL => named_cols.approval_datetime = col,
-            TIME_BIDDING => named_cols.bidding_datetime = col,
-            NUMBER => named_cols.registry_number = col,
-            STATUS => named_cols.status = col,
-            PURCHASE_SUBJECT => named_cols.purchase_subject = col,
-            CUSTOMER_TYPE => named_cols.customer_type = col,
-            ESTIMATION => named_cols.estimation = col,
-            REGION => named_cols.region = col,
-            MAX_PRICE => named_cols.max_price = col,
-            WINNER => named_cols.winner = col,
-            WINNER_PRICE => named_cols.winner_price = col,
-            PARTICIPANTS => named_cols.participants = col,
-            PURCHASE_TYPE => named_cols.purchase_type = col,
-            PURCHASE_SUBJECT_ABBR => named_cols.purchase_abbr = col,
-            OUR_PARTICIPANTS => named_cols.our_participants = col,
-            ETP => named_cols.etp = col,
-            APPLICATION_GUARANTEE => named_cols.application_guarantee = col,
-            CONTRACT_GUARANTEE => named_cols.contract_guarantee = col,
-            _ => continue,
+/// Checks DATEMODE record of the globals of xls 'Workbook' stream.
+/// None if the stream can't be found
+fn xls_is_1904(buf: &[u8]) -> Option<bool> {
+    let cfb = CompoundFile::new(buf)?;
+    let stream = cfb.stream("Workbook").or_else(|| cfb.stream("Book"))?;
+
+    let mut pos = 0;
+    while let Some(header) = stream.get(pos..pos + 4) {
+        let typ = u16::from_le_bytes([header[0], header[1]]);
+        let size = u16::from_le_bytes([header[2], header[3]]) as usize;
+        pos += 4;
+        match typ {
+            XLS_DATEMODE => return stream.get(pos..pos + 2).map(|b| b[0] == 1),
+            // globals are over, the record is optional
+            XLS_EOF => return Some(false),
+            _ => pos += size,
         }
     }
+    Some(false)
+}
 
-    named_cols
+/// Minimal reader of compound file (OLE2) streams that xls is kept in
+struct CompoundFile<'a> {
+    buf: &'a [u8],
+    sector_size: usize,
+    fat: Vec<u32>,
 }
 
-fn active_state_cells(rng: Range<DataType>, cols: NamedCols) -> Vec<Purchase> {
-    let cut_off_date = today_in_excel_date() - HOW_FAR_IN_PAST_DAYS as f64;
+impl<'a> CompoundFile<'a> {
+    fn new(buf: &'a [u8]) -> Option<Self> {
+        if !buf.starts_with(&CFB_SIGNATURE) {
+            return None;
+        }
+        let sector_size = match read_u16(buf, 0x1E)? {
+            9 => 512,
+            12 => 4096,
+            _ => return None,
+        };
+        let mut cfb = CompoundFile {
+            buf,
+            sector_size,
+            fat: Vec::new(),
+        };
 
-    // filter active rows and those that satisfy temporal criteria
-    let rows = rng.rows().take(WORKBOOK_MAX_ROWS).filter(|r| {
-        match (&r[cols.status], &r[cols.bidding_date]) {
-            (DataType::String(s), DataType::DateTime(dt)) => {
-                dt > &cut_off_date && is_active_state(&s)
-            }
-            _ => false,
+        // first 109 ids of FAT sectors are in the header, the rest are in DIFAT chain
+        let mut fat_sectors = read_u32s(buf.get(0x4C..0x200)?);
+        let mut difat = read_u32(buf, 0x44)?;
+        while difat < CFB_MAX_SECTOR && fat_sectors.len() <= buf.len() / 4 {
+            let mut ids = read_u32s(cfb.sector(difat)?);
+            difat = ids.pop()?;
+            fat_sectors.extend(ids);
         }
-    });
+        for id in fat_sectors.into_iter().filter(|&id| id < CFB_MAX_SECTOR) {
+            let sector = read_u32s(cfb.sector(id)?);
+            cfb.fat.extend(sector);
+        }
+        Some(cfb)
+    }
 
-    let mut purches: Vec<Purchase> = Vec::new();
+    fn sector(&self, id: u32) -> Option<&'a [u8]> {
+        // the header takes the first sector
+        let start = (id as usize + 1) * self.sector_size;
+        self.buf.get(start..start + self.sector_size)
+    }
 
-    for r in rows {
-        // if we get just time from excel cell i.e. cell value less than 1.0
-        let bid_datetime = match r[cols.bidding_datetime].get_float().unwrap_or_default() {
-            // than we take bidding_date
-            d if d < 1.0 => r[cols.bidding_date].get_float().unwrap_or_default(),
-            d => d,
+    /// Returns the stream of the root storage by its name
+    fn stream(&self, name: &str) -> Option<Vec<u8>> {
+        let dir = chain(&self.fat, read_u32(self.buf, 0x30)?, |id| self.sector(id))?;
+        // the root entry goes first, it's the start of the mini stream
+        let root = dir.get(..128)?;
+        let entry = dir.chunks_exact(128).find(|e| {
+            let len = (read_u16(e, 64).unwrap_or(0) as usize).min(64);
+            let utf16: Vec<u16> = e[..len]
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|&c| c != 0)
+                .collect();
+            e[66] == 2 && String::from_utf16_lossy(&utf16).eq_ignore_ascii_case(name)
+        })?;
+        let (start, size) = (read_u32(entry, 116)?, read_u32(entry, 120)? as usize);
+
+        let mut data = if size < read_u32(self.buf, 0x38)? as usize {
+            let mini_stream = chain(&self.fat, read_u32(root, 116)?, |id| self.sector(id))?;
+            let mini_fat = chain(&self.fat, read_u32(self.buf, 0x3C)?, |id| self.sector(id))?;
+            chain(&read_u32s(&mini_fat), start, |id| {
+                let start = id as usize * CFB_MINI_SECTOR_SIZE;
+                mini_stream.get(start..start + CFB_MINI_SECTOR_SIZE)
+            })?
+        } else {
+            chain(&self.fat, start, |id| self.sector(id))?
         };
-        let col_datetime = match r[cols.collecting_datetime].get_float().unwrap_or_default() {
-            d if d < 1.0 => r[cols.collecting_date].get_float().unwrap_or_default(),
-            d => d,
-        };
-
-        // get the values from the cells
-        purches.push(Purchase {
-            registry_number: r[cols.registry_number]
-                .get_string()
-                .unwrap_or_default()
-                .replace("№", ""),
-            purchase_subject: r[cols.purchase_subject]
-                .get_string()
-                .unwrap_or_default()
-                .to_owned(),
-            purchase_abbr: r[cols.purchase_abbr]
-                .get_string()
-                .unwrap_or_default()
-                .to_owned(),
-            purchase_type: r[cols.purchase_type]
-                .get_string()
-                .unwrap_or_default()
-                .to_owned(),
-            region: r[cols.region].get_string().unwrap_or_default().to_owned(),
-            customer_type: r[cols.customer_type]
-                .get_string()
-                .unwrap_or_default()
-                .to_owned(),
-            max_price: r[cols.max_price].get_float().unwrap_or_default(),
-            application_guarantee: r[cols.application_guarantee]
-                .get_float()
-                .unwrap_or_default(),
-            contract_guarantee: r[cols.contract_guarantee].get_float().unwrap_or_default(),
-            estimation: r[cols.estimation].get_float().unwrap_or_default(),
-            our_participants: r[cols.our_participants]
-                .get_string()
-                .unwrap_or_default()
-                .to_owned(),
-            etp: r[cols.etp].get_string().unwrap_or_default().to_owned(),
-            winner: r[cols.winner].get_string().unwrap_or_default().to_owned(),
-            winner_price: r[cols.winner_price].get_float().unwrap_or_default(),
-            participants: r[cols.participants]
-                .get_string()
-                .unwrap_or_default()
-                .to_owned(),
-            bidding_datetime: from_excel_date(bid_datetime),
-            collecting_datetime: from_excel_date(col_datetime),
-            approval_datetime: from_excel_date(match r[cols.approval_datetime] {
-                DataType::DateTime(dt) => dt,
-                _ => 0.0,
-            }),
-            status: r[cols.status].get_string().unwrap_or_default().to_owned(),
-        });
+        if data.len() < size {
+            return None;
+        }
+        data.truncate(size);
+        Some(data)
     }
-    purches
 }
 
-fn is_expectable(s: &str) -> bool {
-    match s {
-        DATE_COLLECTING_BIDS => true,
-        TIME_COLLECTING_BIDS => true,
-        DATE_APPROVAL => true,
-        DATE_BIDDING => true,
-        TIME_BIDDING => true,
-        MAX_PRICE => true,
-        NUMBER => true,
-        WINNER => true,
-        STATUS => true,
-        WINNER_PRICE => true,
-        PARTICIPANTS => true,
-        PURCHASE_TYPE => true,
-        PURCHASE_SUBJECT => true,
-        PURCHASE_SUBJECT_ABBR => true,
-        OUR_PARTICIPANTS => true,
-        ETP => true,
-        APPLICATION_GUARANTEE => true,
-        CONTRACT_GUARANTEE => true,
-        CUSTOMER_TYPE => true,
-        ESTIMATION => true,
-        REGION => true,
-        _ => false,
-    }
-}
-
-fn is_active_state(s: &str) -> bool {
-    match s {
-        STATUS_GO => true,
-        STATUS_ADMITTED => true,
-        STATUS_APPLY => true,
-        STATUS_WIN => true,
-        STATUS_LOSS => true,
-        STATUS_ESTIMATION => true,
-        _ => false,
+/// Collects the sectors of the chain that starts with `start` and goes by the table
+fn chain<'s>(
+    table: &[u32],
+    start: u32,
+    sector: impl Fn(u32) -> Option<&'s [u8]>,
+) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut id = start;
+    // a chain can't be longer than the table, otherwise it's a loop
+    for _ in 0..=table.len() {
+        if id >= CFB_MAX_SECTOR {
+            return Some(data);
+        }
+        data.extend_from_slice(sector(id)?);
+        id = *table.get(id as usize)?;
     }
+    None
 }
 
-/// Converts an excel date which is just a float number
-/// to formated time string as in RFS3339
-fn from_excel_date(excel_date: f64) -> String {
-    let days = excel_date - EXCEL_UNIX_EPOCH as f64;
-    let seconds = seconds_from_days(days);
-    let duration = Duration::new(seconds, 0);
-    Moment::from_duration_since_epoch(duration).to_string()
+fn read_u16(buf: &[u8], pos: usize) -> Option<u16> {
+    buf.get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
 }
 
-/// Returns today's date in excel representation
-/// which is a float number
-fn today_in_excel_date() -> f64 {
-    if let Ok(duration) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-        let d_since_epoch = days_from_seconds(duration.as_secs());
-        let today_excel = d_since_epoch + EXCEL_UNIX_EPOCH;
-        today_excel as f64
-    } else {
-        0.0
-    }
+fn read_u32(buf: &[u8], pos: usize) -> Option<u32> {
+    buf.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
 }
 
-const fn days_from_seconds(timestamp_secs: u64) -> u64 {
-    timestamp_secs / 86400
+fn read_u32s(buf: &[u8]) -> Vec<u32> {
+    buf.chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
 }
 
-fn seconds_from_days(days: f64) -> u64 {
-    (days * 86400.0).round() as u64
+/// Returns a vector of active state [Purchase]'s if any.
+/// Rows of every sheet that has named ranges are merged.
+/// Fails if the workbook columns doesn't match the schema,
+/// so that garbage records are never built
+fn active_purchases(
+    workbook: &mut Sheets,
+    dates: DateSystem,
+    config: &WorkbookConfig,
+    issues: &mut Vec<CellIssue>,
+) -> ExcelResult<Option<Vec<Purchase>>> {
+    // this function heavily relias on named ranges in workbook
+    // or column headers and expected that they are equal
+    // to those that are described by schema
+    let named_ranges = named_ranges(workbook, &config.schema);
+
+    let sheets = sheet_ranges(workbook, &named_ranges, config);
+    merged_purchases(sheets, dates, config, issues)
 }
 
-#[cfg(test)]
-mod tests {
+/// Returns active state [Purchase]'s of the sheets merged in their order.
+/// Fails if columns of any sheet doesn't match the schema
+fn merged_purchases(
+    sheets: Vec<SheetColumns>,
+    dates: DateSystem,
+    config: &WorkbookConfig,
+    issues: &mut Vec<CellIssue>,
+) -> ExcelResult<Option<Vec<Purchase>>> {
+    if sheets.is_empty() {
+        return Err(WorkbookError::SchemaError(vec![no_sheets_check(config)]));
+    }
+    if sheets.iter().any(|s| s.check.is_broken()) {
+        let broken = sheets
+            .into_iter()
+            .map(|s| s.check)
+            .filter(ColumnsCheck::is_broken)
+            .collect();
+        return Err(WorkbookError::SchemaError(broken));
+    }
 
-    use super::*;
+    let mut purches = Vec::new();
+    for sheet in sheets {
+        purches.extend(sheet_purchases(sheet, dates, config, issues));
+    }
+    let purches = dedup(purches, config.duplicates)?;
 
-    #[test]
-    fn test_print_active_state() {
-        let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
-        assert!(print_active_state(Path::new(&wb_path)).is_ok());
+    if !purches.is_empty() {
+        Ok(Some(purches))
+    } else {
+        Ok(None)
     }
+}
 
-    #[test]
-    fn test_print_active_state_json() {
-        let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
-        assert!(print_active_state_json(Path::new(&wb_path)).is_ok());
+/// Returns a vector of active state [Purchase]'s of the table
+/// which columns are located by the header row only e.g. csv file
+pub fn active_state_range(
+    range: Range<DataType>,
+    config: &WorkbookConfig,
+) -> ExcelResult<CheckedRecords> {
+    let sheet = SheetColumns::by_headers(range, config);
+    if sheet.check.is_broken() {
+        return Err(WorkbookError::SchemaError(vec![sheet.check]));
     }
 
-    #[test]
-    fn test_active_state_json() {
-        let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
-        assert!(active_state_json(Path::new(&wb_path)).is_ok());
+    let mut issues = Vec::new();
+    // every cell of the text table is a string, but the system may be set by config
+    let dates = config.date_system.unwrap_or_default();
+    let purches = sheet_purchases(sheet, dates, config, &mut issues);
+    let purches = dedup(purches, config.duplicates)?;
+    if !purches.is_empty() {
+        Ok((Some(purches), issues))
+    } else {
+        Ok((None, issues))
     }
+}
 
-    #[test]
-    fn test_active_state() {
-        let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
-        assert!(active_state(Path::new(&wb_path)).is_ok());
+/// Checks the columns of the table that are located by the header row
+pub fn check_range_columns(range: Range<DataType>, config: &WorkbookConfig) -> ColumnsCheck {
+    SheetColumns::by_headers(range, config).check
+}
+
+/// Returns active state [Purchase]'s of the sheet
+/// which columns are already checked
+fn sheet_purchases(
+    sheet: SheetColumns,
+    dates: DateSystem,
+    config: &WorkbookConfig,
+    issues: &mut Vec<CellIssue>,
+) -> Vec<Purchase> {
+    if !sheet.check.is_ok() {
+        warn!("{}; these fields are left empty", sheet.check);
+    }
+    let missing: Vec<&str> = config
+        .extra
+        .keys()
+        .filter(|k| !sheet.extra_cols.contains_key(*k))
+        .collect();
+    if !missing.is_empty() {
+        let prefix = match sheet.name.as_str() {
+            "" => String::new(),
+            name => format!("sheet '{}': ", name),
+        };
+        warn!(
+            "{}missing extra [{}]; these values are left empty",
+            prefix,
+            missing.join(", ")
+        );
     }
 
-    #[test]
-    fn test_open_wb() {
-        let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
-        assert!(open_wb(Path::new(&wb_path)).is_ok());
+    let mut named_cols = named_cols(&sheet.field_cols);
+    named_cols.extra = sheet.extra_cols.into_iter().collect();
+    active_state_cells(sheet.range, named_cols, &sheet.name, dates, config, issues)
+}
+
+/// Check result of the workbook without any sheets
+fn no_sheets_check(config: &WorkbookConfig) -> ColumnsCheck {
+    ColumnsCheck::new("", &[], &HashMap::new(), config)
+}
+
+/// Returns records that are active and satisfy the same
+/// temporal criteria as workbook rows e.g. records from json file.
+/// Active records that are lost because of their values are reported
+pub fn active_records(
+    purches: Vec<Purchase>,
+    config: &WorkbookConfig,
+) -> ExcelResult<CheckedRecords> {
+    let cut_off_date = today_in_excel_date(config.timezone) - config.how_far_in_past_days as f64;
+    let mut issues = Vec::new();
+
+    // the limit is of the records that are read as it's of the sheet rows
+    let purches: Vec<Purchase> = purches
+        .into_iter()
+        .take(config.max_rows)
+        .enumerate()
+        .filter_map(|(i, mut p)| {
+            // records of the old snapshots have only the text
+            if p.participants_list.is_empty() {
+                p.participants_list = participants::parse(&p.participants);
+            }
+            if p.our_participants_list.is_empty() {
+                p.our_participants_list = participants::parse(&p.our_participants);
+            }
+            // records of our own snapshots keep where they came from
+            if p.path.is_empty() {
+                p.path = config.path.clone();
+                p.row = i as u32 + 1;
+            }
+            // status as it is written in config
+            let status = config.statuses.active(&p.status)?.to_owned();
+            let bidding_date = match parse_iso_moment(&p.bidding_datetime, config.offset(&p.region))
+            {
+                Some(dt) => dt,
+                None => {
+                    let problem = if p.bidding_datetime.is_empty() {
+                        Problem::MissingValue
+                    } else {
+                        Problem::InvalidDate
+                    };
+                    issues.push(p.issue(Field::BiddingDatetime, problem, &p.bidding_datetime));
+                    return None;
+                }
+            };
+            if bidding_date.trunc() <= cut_off_date {
+                return None;
+            }
+            if p.registry_number.is_empty() {
+                issues.push(p.issue(Field::RegistryNumber, Problem::MissingValue, ""));
+                return None;
+            }
+            p.status = status;
+            Some(p)
+        })
+        .collect();
+    let purches = dedup(purches, config.duplicates)?;
+
+    if !purches.is_empty() {
+        Ok((Some(purches), issues))
+    } else {
+        Ok((None, issues))
     }
+}
 
-    #[test]
-    fn test_from_excel_date() {
-        assert_eq!(
-            from_excel_date(44516.42361),
-            "2021-11-16T10:10:00+00:00".to_string()
-        );
-        assert_eq!(
-            from_excel_date(44621.00),
-            "2022-03-01T00:00:00+00:00".to_string()
-        );
-        assert_eq!(
-            from_excel_date(43890.4166666667),
-            "2020-02-29T10:00:00+00:00".to_string()
-        );
-        assert_eq!(
-            from_excel_date(44713.00),
-            "2022-06-01T00:00:00+00:00".to_string()
-        );
+/// Two records with the same registry and lot numbers
+#[derive(Debug)]
+pub struct Duplicate {
+    registry_number: String,
+    lot_number: String,
+    first: (String, u32), // sheet and row of the first record
+    second: (String, u32),
+}
+
+impl Duplicate {
+    fn new(first: &Purchase, second: &Purchase) -> Self {
+        Self {
+            registry_number: first.registry_number.clone(),
+            lot_number: first.lot_number.clone(),
+            first: (first.sheet.clone(), first.row),
+            second: (second.sheet.clone(), second.row),
+        }
     }
+}
 
-    #[test]
-    fn test_is_active_state() {
-        assert!(is_active_state(STATUS_GO));
-        assert!(is_active_state(STATUS_ADMITTED));
-        assert!(is_active_state(STATUS_APPLY));
-        assert!(is_active_state(STATUS_WIN));
-        assert!(is_active_state(STATUS_LOSS));
-        assert!(is_active_state(STATUS_ESTIMATION));
-        assert!(!is_active_state("invalid status"))
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let place = |(sheet, row): &(String, u32)| match sheet.as_str() {
+            "" => format!("row {}", row),
+            s => format!("sheet '{}' row {}", s, row),
+        };
+        write!(f, "registry number '{}'", self.registry_number)?;
+        if !self.lot_number.is_empty() {
+            write!(f, " lot '{}'", self.lot_number)?;
+        }
+        write!(
+            f,
+            " is in {} and {}",
+            place(&self.first),
+            place(&self.second)
+        )
     }
+}
 
-    #[test]
-    fn test_is_expectable() {
-        assert!(is_expectable(DATE_COLLECTING_BIDS));
-        assert!(is_expectable(TIME_COLLECTING_BIDS));
-        assert!(is_expectable(DATE_APPROVAL));
-        assert!(is_expectable(DATE_BIDDING));
-        assert!(is_expectable(TIME_BIDDING));
-        assert!(is_expectable(MAX_PRICE));
-        assert!(is_expectable(NUMBER));
-        assert!(is_expectable(WINNER));
-        assert!(is_expectable(STATUS));
-        assert!(is_expectable(WINNER_PRICE));
-        assert!(is_expectable(PARTICIPANTS));
-        assert!(is_expectable(PURCHASE_TYPE));
-        assert!(is_expectable(PURCHASE_SUBJECT));
-        assert!(is_expectable(PURCHASE_SUBJECT_ABBR));
-        assert!(is_expectable(OUR_PARTICIPANTS));
-        assert!(is_expectable(ETP));
-        assert!(is_expectable(APPLICATION_GUARANTEE));
-        assert!(is_expectable(CONTRACT_GUARANTEE));
-        assert!(is_expectable(CUSTOMER_TYPE));
-        assert!(is_expectable(ESTIMATION));
-        assert!(is_expectable(REGION));
-        assert!(!is_expectable("invalid name"))
+/// Leaves one record of those that have the same registry and lot numbers
+/// as the policy says or fails if the policy rejects duplicates
+fn dedup(purches: Vec<Purchase>, policy: Duplicates) -> ExcelResult<Vec<Purchase>> {
+    let mut result: Vec<Purchase> = Vec::with_capacity(purches.len());
+    let mut index: HashMap<(String, String), usize> = HashMap::with_capacity(purches.len());
+    let mut duplicates = Vec::new();
+
+    for p in purches {
+        match index.get(&p.key()) {
+            Some(&i) => {
+                let duplicate = Duplicate::new(&result[i], &p);
+                match policy {
+                    Duplicates::FirstWins => warn!("{}; the first one is taken", duplicate),
+                    Duplicates::LastWins => {
+                        warn!("{}; the last one is taken", duplicate);
+                        result[i] = p;
+                    }
+                    Duplicates::Reject => (),
+                }
+                duplicates.push(duplicate);
+            }
+            None => {
+                index.insert(p.key(), result.len());
+                result.push(p);
+            }
+        }
+    }
+
+    if policy == Duplicates::Reject && !duplicates.is_empty() {
+        return Err(WorkbookError::DuplicateError(duplicates));
+    }
+    Ok(result)
+}
+
+/// Sheet to read with it's columns
+struct SheetColumns {
+    name: String,
+    range: Range<DataType>,
+    field_cols: HashMap<Field, Location>,
+    extra_cols: HashMap<String, Location>,
+    check: ColumnsCheck,
+}
+
+impl SheetColumns {
+    /// Locates columns of the sheet by it's named ranges and the header row
+    /// as it's set by `workbook.columns`. Columns of the sheet that can't be
+    /// read are missing
+    fn new(
+        name: String,
+        range: Option<Range<DataType>>,
+        named_ranges: &[NamedRange],
+        extra_ranges: &[NamedRange<String>],
+        config: &WorkbookConfig,
+    ) -> Self {
+        let (range, mut field_cols, mut extra_cols) = match range {
+            Some(range) => (
+                range,
+                named_range_cols(named_ranges),
+                named_range_cols(extra_ranges),
+            ),
+            None => (Range::empty(), HashMap::new(), HashMap::new()),
+        };
+        if config.columns != Columns::NamedRanges {
+            for (field, col) in header_cols(&range, config.header_row, |s| config.headers.field(s))
+            {
+                field_cols.entry(field).or_insert(col);
+            }
+            let headers = header_cols(&range, config.header_row, |s| {
+                config.extra.key(s).map(String::from)
+            });
+            for (key, col) in headers {
+                extra_cols.entry(key).or_insert(col);
+            }
+        }
+
+        let check = ColumnsCheck::new(&name, named_ranges, &field_cols, config);
+        Self {
+            name,
+            range,
+            field_cols,
+            extra_cols,
+            check,
+        }
+    }
+
+    /// Locates columns of the table by the header row only
+    fn by_headers(range: Range<DataType>, config: &WorkbookConfig) -> Self {
+        let field_cols = header_cols(&range, config.header_row, |s| config.headers.field(s));
+        let extra_cols = header_cols(&range, config.header_row, |s| {
+            config.extra.key(s).map(String::from)
+        });
+        let check = ColumnsCheck::new("", &[], &field_cols, config);
+        Self {
+            name: String::new(),
+            range,
+            field_cols,
+            extra_cols,
+            check,
+        }
+    }
+}
+
+/// Returns the sheets to read and their columns located in the way
+/// that is set by `workbook.columns`. Every sheet that is referred
+/// by named ranges is read, if there is none than `workbook.sheet`
+/// or the first sheet is read
+fn sheet_ranges(
+    workbook: &mut Sheets,
+    named_ranges: &[NamedRange],
+    config: &WorkbookConfig,
+) -> Vec<SheetColumns> {
+    let (named_ranges, extra_ranges) = match config.columns {
+        Columns::Headers => (&[][..], Vec::new()),
+        Columns::NamedRanges | Columns::Hybrid => {
+            (named_ranges, extra_ranges(workbook, &config.extra))
+        }
+    };
+
+    // sheets in the workbook order
+    let mut sheets: Vec<String> = workbook
+        .sheet_names()
+        .iter()
+        .filter(|s| named_ranges.iter().any(|nr| &nr.sheet == *s))
+        .cloned()
+        .collect();
+    // named ranges that refer to the unknown sheet
+    for nr in named_ranges {
+        if !sheets.contains(&nr.sheet) {
+            sheets.push(nr.sheet.clone());
+        }
+    }
+    if sheets.is_empty() {
+        match config
+            .sheet
+            .clone()
+            .or_else(|| workbook.sheet_names().first().cloned())
+        {
+            Some(sheet) => sheets.push(sheet),
+            None => return Vec::new(),
+        }
+    }
+
+    let mut result = Vec::with_capacity(sheets.len());
+    for name in sheets {
+        let sheet_named_ranges: Vec<NamedRange> = named_ranges
+            .iter()
+            .filter(|nr| nr.sheet == name)
+            .cloned()
+            .collect();
+
+        let sheet_extra_ranges: Vec<NamedRange<String>> = extra_ranges
+            .iter()
+            .filter(|nr| nr.sheet == name)
+            .cloned()
+            .collect();
+
+        let range = workbook.worksheet_range(&name).and_then(|r| r.ok());
+        result.push(SheetColumns::new(
+            name,
+            range,
+            &sheet_named_ranges,
+            &sheet_extra_ranges,
+            config,
+        ));
+    }
+
+    result
+}
+
+enum ColumnPosition {
+    Left = 0,
+    Right = 1,
+}
+
+/// Cells of the sheet that are covered by the field.
+/// Columns and rows are absolute, starting from 0 and inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Location {
+    left: usize,
+    right: usize,
+    rows: Option<(u32, u32)>, // none if the whole column is covered
+}
+
+impl Location {
+    fn column(col: usize) -> Self {
+        Self {
+            left: col,
+            right: col,
+            rows: None,
+        }
+    }
+
+    fn contains_row(&self, row: u32) -> bool {
+        match self.rows {
+            Some((top, bottom)) => top <= row && row <= bottom,
+            None => true,
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+struct NamedCols {
+    registry_number: Option<Location>,
+    lot_number: Option<Location>,
+    purchase_subject: Option<Location>,
+    purchase_abbr: Option<Location>,
+    purchase_type: Option<Location>,
+    collecting_datetime: Option<Location>,
+    approval_datetime: Option<Location>,
+    bidding_datetime: Option<Location>,
+    bidding_date: Option<Location>,
+    collecting_date: Option<Location>,
+    region: Option<Location>,
+    customer_type: Option<Location>,
+    max_price: Option<Location>,
+    application_guarantee: Option<Location>,
+    contract_guarantee: Option<Location>,
+    status: Option<Location>,
+    our_participants: Option<Location>,
+    estimation: Option<Location>,
+    etp: Option<Location>,
+    winner: Option<Location>,
+    winner_price: Option<Location>,
+    participants: Option<Location>,
+    extra: BTreeMap<String, Location>,
+}
+
+impl NamedCols {
+    /// Returns location of the field
+    fn get(&self, field: Field) -> Option<Location> {
+        match field {
+            Field::RegistryNumber => self.registry_number,
+            Field::LotNumber => self.lot_number,
+            Field::PurchaseSubject => self.purchase_subject,
+            Field::PurchaseAbbr => self.purchase_abbr,
+            Field::PurchaseType => self.purchase_type,
+            Field::CollectingDate => self.collecting_date,
+            Field::CollectingDatetime => self.collecting_datetime,
+            Field::ApprovalDatetime => self.approval_datetime,
+            Field::BiddingDate => self.bidding_date,
+            Field::BiddingDatetime => self.bidding_datetime,
+            Field::Region => self.region,
+            Field::CustomerType => self.customer_type,
+            Field::MaxPrice => self.max_price,
+            Field::ApplicationGuarantee => self.application_guarantee,
+            Field::ContractGuarantee => self.contract_guarantee,
+            Field::Status => self.status,
+            Field::OurParticipants => self.our_participants,
+            Field::Estimation => self.estimation,
+            Field::Etp => self.etp,
+            Field::Winner => self.winner,
+            Field::WinnerPrice => self.winner_price,
+            Field::Participants => self.participants,
+        }
+    }
+}
+
+/// This type is represent a row in excel workbook
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Purchase {
+    registry_number: String,
+    // empty if the purchase has the only lot
+    #[serde(default)]
+    lot_number: String,
+    purchase_subject: String,
+    purchase_abbr: String,
+    purchase_type: String,
+    collecting_datetime: String,
+    approval_datetime: String,
+    bidding_datetime: String,
+    region: String,
+    customer_type: String,
+    max_price: Money,
+    application_guarantee: Money,
+    contract_guarantee: Money,
+    status: String,
+    our_participants: String,
+    estimation: Money,
+    etp: String,
+    winner: String,
+    winner_price: Money,
+    participants: String,
+    // parsed from the text fields, the text is kept as is
+    #[serde(default)]
+    our_participants_list: Vec<Participant>,
+    #[serde(default)]
+    participants_list: Vec<Participant>,
+    // where the record is read from, it's not compared
+    // so moving rows around doesn't make records changed
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    sheet: String,
+    // row of the sheet or number of the record in json, starts from 1
+    #[serde(default)]
+    row: u32,
+    // address of every field cell e.g. {"max_price": "L512"} if enabled by config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cells: Option<BTreeMap<Field, String>>,
+    // values of the extra columns as they are typed in the cells,
+    // they are compared only if it's set by config
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    extra: BTreeMap<String, Value>,
+    // fields that are changed since the previous snapshot, only in the diff
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    changes: Vec<FieldChange>,
+}
+
+/// Field of the record that is changed e.g. moved auction time.
+/// Extra columns are named as 'extra.<key>'
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    field: String,
+    old: Value,
+    new: Value,
+}
+
+impl FieldChange {
+    fn new(field: &str, old: Value, new: Value) -> Self {
+        Self {
+            field: field.to_owned(),
+            old,
+            new,
+        }
+    }
+}
+
+impl Purchase {
+    /// Identity of the record. Lots of the same
+    /// purchase have the same registry number
+    fn key(&self) -> (String, String) {
+        (self.registry_number.clone(), self.lot_number.clone())
+    }
+
+    /// Issue of the record field e.g. of json file. The column is known
+    /// only if the record keeps the addresses of its cells
+    fn issue(&self, field: Field, problem: Problem, value: &str) -> CellIssue {
+        let column = self
+            .cells
+            .as_ref()
+            .and_then(|cells| cells.get(&field))
+            .map(|a| a.trim_end_matches(|c: char| c.is_ascii_digit()).to_owned())
+            .unwrap_or_default();
+        CellIssue {
+            sheet: self.sheet.clone(),
+            row: self.row,
+            column,
+            field,
+            problem,
+            value: value.to_owned(),
+        }
+    }
+}
+
+impl Purchase {
+    /// Returns the fields that differ in the `new` record in the order of
+    /// the record. Every data field is compared, the lists of participants
+    /// are compared by their text. Where the record is read from (path,
+    /// sheet, row and cells) isn't compared, as well as the extra columns
+    /// which aren't set to be compared
+    fn field_changes(&self, new: &Self, extras: &Extras) -> Vec<FieldChange> {
+        // old and new values of the field
+        let text = |get: fn(&Purchase) -> &String| {
+            (
+                Value::from(get(self).as_str()),
+                Value::from(get(new).as_str()),
+            )
+        };
+        let money = |get: fn(&Purchase) -> Money| {
+            (
+                Value::from(get(self).to_rubles()),
+                Value::from(get(new).to_rubles()),
+            )
+        };
+
+        let fields = [
+            (Field::RegistryNumber, text(|p| &p.registry_number)),
+            (Field::LotNumber, text(|p| &p.lot_number)),
+            (Field::PurchaseSubject, text(|p| &p.purchase_subject)),
+            (Field::PurchaseAbbr, text(|p| &p.purchase_abbr)),
+            (Field::PurchaseType, text(|p| &p.purchase_type)),
+            (Field::CollectingDatetime, text(|p| &p.collecting_datetime)),
+            (Field::ApprovalDatetime, text(|p| &p.approval_datetime)),
+            (Field::BiddingDatetime, text(|p| &p.bidding_datetime)),
+            (Field::Region, text(|p| &p.region)),
+            (Field::CustomerType, text(|p| &p.customer_type)),
+            (Field::MaxPrice, money(|p| p.max_price)),
+            (
+                Field::ApplicationGuarantee,
+                money(|p| p.application_guarantee),
+            ),
+            (Field::ContractGuarantee, money(|p| p.contract_guarantee)),
+            (Field::Status, text(|p| &p.status)),
+            (Field::OurParticipants, text(|p| &p.our_participants)),
+            (Field::Estimation, money(|p| p.estimation)),
+            (Field::Etp, text(|p| &p.etp)),
+            (Field::Winner, text(|p| &p.winner)),
+            (Field::WinnerPrice, money(|p| p.winner_price)),
+            (Field::Participants, text(|p| &p.participants)),
+        ];
+        let mut changes: Vec<FieldChange> = fields
+            .iter()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(field, (old, new))| FieldChange::new(field.as_str(), old.clone(), new.clone()))
+            .collect();
+
+        // missing value is the same as empty one
+        for key in extras.compared() {
+            let old = self.extra.get(key).cloned().unwrap_or_default();
+            let new = new.extra.get(key).cloned().unwrap_or_default();
+            if old != new {
+                changes.push(FieldChange::new(&format!("extra.{}", key), old, new));
+            }
+        }
+
+        changes
+    }
+}
+
+// [Purchase] equivalence logic
+impl PartialEq for Purchase {
+    fn eq(&self, other: &Self) -> bool {
+        self.field_changes(other, &Extras::default()).is_empty()
+    }
+}
+
+/// Defined name of the field or of the extra column
+#[derive(Clone)]
+struct NamedRange<K = Field> {
+    field: K,
+    sheet: String, // sheet portion of 'List1!$A$:$A$' == 'List1'
+    range: String, // range portion of 'List1!$A$:$A$' == '$A$:$A$'
+}
+
+impl<K> NamedRange<K> {
+    pub fn new(field: K, def_name: &(String, String)) -> Self {
+        let (sheet, range) = Self::parse_reference(&def_name.1);
+        Self {
+            field,
+            sheet: Self::unquote_sheet(sheet),
+            range,
+        }
+    }
+
+    /// Splits reference to sheet and range portions.
+    /// Excel references looks like 'List1!$A:$A' and
+    /// OpenDocument ones like '$List1.$A$1:.$A$100'
+    fn parse_reference(s: &str) -> (&str, String) {
+        if let Some((sheet, range)) = s.rsplit_once('!') {
+            return (sheet, range.to_owned());
+        }
+
+        let mut sheet = "";
+        let mut cells = Vec::new();
+        for part in s.split(':') {
+            match part.rsplit_once('.') {
+                Some((sh, cell)) => {
+                    if sheet.is_empty() {
+                        sheet = sh;
+                    }
+                    cells.push(cell);
+                }
+                None => cells.push(part),
+            }
+        }
+        (sheet, cells.join(":"))
+    }
+
+    /// Removes quotes and absolute reference sign from the sheet name
+    /// e.g. '$'My list'' == 'My list'
+    fn unquote_sheet(s: &str) -> String {
+        let s = s.trim_start_matches('$');
+        match s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+            Some(s) => s.replace("''", "'"),
+            None => s.to_owned(),
+        }
+    }
+
+    /// Returns column char name
+    pub fn col_name(&self, cp: ColumnPosition) -> String {
+        self.range
+            .split(":")
+            .nth(cp as usize)
+            .unwrap_or_default()
+            .replace("$", "")
+            .trim_end_matches(|c: char| c.is_ascii_digit()) // row portion e.g. 'A1'
+            .to_owned()
+    }
+
+    /// Returns row number of the range side e.g. 'B5' == 4.
+    /// None if the range covers the whole column
+    pub fn row_num(&self, cp: ColumnPosition) -> Option<u32> {
+        let cell = self.range.split(':').nth(cp as usize)?.replace('$', "");
+        let row: u32 = cell
+            .trim_start_matches(|c: char| c.is_ascii_alphabetic())
+            .parse()
+            .ok()?;
+        row.checked_sub(1)
+    }
+
+    /// Returns the cells that are covered by the range
+    /// e.g. '$B$5:$C$900' or '$B:$B'
+    fn location(&self) -> ExcelResult<Location> {
+        let invalid = || WorkbookError::InvalidColumnNameError(self.range.clone());
+
+        let left = self.col_num(ColumnPosition::Left)?;
+        // single cell range has no right side
+        let right = if self.range.contains(':') {
+            self.col_num(ColumnPosition::Right)?
+        } else {
+            left
+        };
+        if right < left {
+            return Err(invalid());
+        }
+
+        let rows = match (
+            self.row_num(ColumnPosition::Left),
+            self.row_num(ColumnPosition::Right),
+        ) {
+            (Some(top), Some(bottom)) if top <= bottom => Some((top, bottom)),
+            (Some(top), None) if !self.range.contains(':') => Some((top, top)),
+            (None, None) => None,
+            _ => return Err(invalid()),
+        };
+
+        Ok(Location { left, right, rows })
+    }
+
+    /// Maps column char name to column serial number e.g. 'A' == 1
+    pub fn col_num(&self, cp: ColumnPosition) -> ExcelResult<usize> {
+        let col_name = self.col_name(cp);
+
+        if col_name.is_empty() {
+            return Err(WorkbookError::InvalidColumnNameError(col_name));
+        }
+
+        let mut col = 0;
+        let mut multi = 1;
+
+        for c in col_name.chars().rev() {
+            match c {
+                'A'..='Z' => {
+                    if let (Some(c_num), Some(a_num)) = (c.to_digit(RADIX), 'A'.to_digit(RADIX)) {
+                        col += (c_num - a_num + 1) * multi;
+                    }
+                }
+                'a'..='z' => {
+                    if let (Some(c_num), Some(a_num)) = (c.to_digit(RADIX), 'a'.to_digit(RADIX)) {
+                        col += (c_num - a_num + 1) * multi;
+                    }
+                }
+                _ => return Err(WorkbookError::InvalidColumnNameError(col_name)),
+            }
+            multi *= 26;
+        }
+
+        if col > TOTAL_COLUMNS {
+            return Err(WorkbookError::InvalidColumnNameError(col_name));
+        }
+
+        Ok(col as usize - 1)
+    }
+}
+
+/// Maps column serial number to column char name e.g. 0 == 'A'
+fn column_name(col: usize) -> String {
+    let mut name = Vec::new();
+    let mut col = col + 1;
+
+    while col > 0 {
+        let rem = (col - 1) % 26;
+        name.push((b'A' + rem as u8) as char);
+        col = (col - 1) / 26;
+    }
+
+    name.iter().rev().collect()
+}
+
+/// Returns a vector of [NamedRange]'s build up from workbook defined names
+/// that are known to schema
+fn named_ranges(workbook: &Sheets, schema: &Schema) -> Vec<NamedRange> {
+    let mut named_ranges: Vec<NamedRange> = Vec::with_capacity(NAMED_RANGES_COUNT);
+
+    for name in workbook.defined_names().iter() {
+        if let Some(field) = schema.field(&name.0) {
+            named_ranges.push(NamedRange::new(field, name));
+        }
+    }
+
+    named_ranges
+}
+
+/// Returns [NamedRange]'s of the extra columns
+fn extra_ranges(workbook: &Sheets, extras: &Extras) -> Vec<NamedRange<String>> {
+    if extras.is_empty() {
+        return Vec::new();
+    }
+
+    workbook
+        .defined_names()
+        .iter()
+        .filter_map(|name| Some(NamedRange::new(extras.key(&name.0)?.to_owned(), name)))
+        .collect()
+}
+
+/// Returns result of mapping [NamedRange]'s to the
+/// cells of the sheet that they cover
+fn named_range_cols<K: Clone + Eq + Hash>(named_ranges: &[NamedRange<K>]) -> HashMap<K, Location> {
+    let mut field_cols = HashMap::with_capacity(named_ranges.len());
+
+    for nr in named_ranges {
+        if let Ok(location) = nr.location() {
+            // the first named range of the field wins
+            field_cols.entry(nr.field.clone()).or_insert(location);
+        }
+    }
+
+    field_cols
+}
+
+/// Returns columns that are located by the text
+/// in the header row. Row number starts from 1.
+/// Only the rows below the header row are covered
+fn header_cols<K, F>(rng: &Range<DataType>, header_row: u32, key: F) -> HashMap<K, Location>
+where
+    K: Eq + Hash,
+    F: Fn(&str) -> Option<K>,
+{
+    let mut field_cols = HashMap::new();
+
+    let (start, end) = match (rng.start(), rng.end()) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return field_cols,
+    };
+
+    for col in start.1..=end.1 {
+        if let Some(DataType::String(s)) = rng.get_value((header_row - 1, col)) {
+            if let Some(field) = key(s) {
+                // the leftmost column wins
+                field_cols.entry(field).or_insert(Location {
+                    rows: Some((header_row, u32::MAX)),
+                    ..Location::column(col as usize)
+                });
+            }
+        }
+    }
+
+    field_cols
+}
+
+/// Returns [NamedCols] struct that is represent result of
+/// mapping [Field]'s to the cells of the sheet
+fn named_cols(field_cols: &HashMap<Field, Location>) -> NamedCols {
+    let mut named_cols = NamedCols::default();
+
+    for (field, col) in field_cols.iter() {
+        let col = Some(*col);
+        match field {
+            Field::BiddingDate => named_cols.bidding_date = col,
+            Field::CollectingDate => named_cols.collecting_date = col,
+            Field::CollectingDatetime => named_cols.collecting_datetime = col,
+            Field::ApprovalDatetime => named_cols.approval_datetime = col,
+            Field::BiddingDatetime => named_cols.bidding_datetime = col,
+            Field::RegistryNumber => named_cols.registry_number = col,
+            Field::LotNumber => named_cols.lot_number = col,
+            Field::Status => named_cols.status = col,
+            Field::PurchaseSubject => named_cols.purchase_subject = col,
+            Field::CustomerType => named_cols.customer_type = col,
+            Field::Estimation => named_cols.estimation = col,
+            Field::Region => named_cols.region = col,
+            Field::MaxPrice => named_cols.max_price = col,
+            Field::Winner => named_cols.winner = col,
+            Field::WinnerPrice => named_cols.winner_price = col,
+            Field::Participants => named_cols.participants = col,
+            Field::PurchaseType => named_cols.purchase_type = col,
+            Field::PurchaseAbbr => named_cols.purchase_abbr = col,
+            Field::OurParticipants => named_cols.our_participants = col,
+            Field::Etp => named_cols.etp = col,
+            Field::ApplicationGuarantee => named_cols.application_guarantee = col,
+            Field::ContractGuarantee => named_cols.contract_guarantee = col,
+        }
+    }
+
+    named_cols
+}
+
+static EMPTY_CELL: DataType = DataType::Empty;
+
+/// Row of the sheet range with it's absolute position
+struct Row<'a> {
+    cells: &'a [DataType],
+    row: u32,         // absolute row number
+    first_col: usize, // absolute column number of the first cell
+    dates: DateSystem,
+}
+
+impl<'a> Row<'a> {
+    /// Returns the cells of the row that are covered by the field
+    /// location. None of them if the row is out of the location
+    fn cells(&self, location: Option<Location>) -> &'a [DataType] {
+        let location = match location {
+            Some(l) if l.contains_row(self.row) => l,
+            _ => return &[],
+        };
+        if location.right < self.first_col || self.cells.is_empty() {
+            return &[];
+        }
+
+        let left = location.left.saturating_sub(self.first_col);
+        let right = (location.right - self.first_col).min(self.cells.len() - 1);
+        self.cells.get(left..=right).unwrap_or(&[])
+    }
+
+    /// Returns the first non-empty cell of the field with it's absolute column
+    fn find(&self, location: Option<Location>) -> Option<(usize, &'a DataType)> {
+        let first = location?.left.max(self.first_col);
+        self.cells(location)
+            .iter()
+            .enumerate()
+            .find(|(_, c)| !c.is_empty())
+            .map(|(i, c)| (first + i, c))
+    }
+
+    /// Returns the first non-empty cell of the field or empty cell
+    fn cell(&self, location: Option<Location>) -> &'a DataType {
+        self.find(location).map(|(_, c)| c).unwrap_or(&EMPTY_CELL)
+    }
+
+    /// Returns excel date of the field that is continuous count of days
+    /// since 30.12.1899 with the offset if it's written in the cell.
+    /// Serials of the workbook are normalized by it's date system
+    fn date(&self, location: Option<Location>) -> Option<ZonedDate> {
+        match self.cell(location) {
+            c @ DataType::DateTime(_) | c @ DataType::Float(_) | c @ DataType::Int(_) => {
+                cell_zoned_date(c).map(|(dt, o)| (self.dates.normalize(dt), o))
+            }
+            c => cell_zoned_date(c),
+        }
+    }
+
+    /// Returns the issue of the field cell
+    fn issue(
+        &self,
+        sheet: &str,
+        field: Field,
+        location: Option<Location>,
+        problem: Problem,
+    ) -> CellIssue {
+        CellIssue {
+            sheet: sheet.to_owned(),
+            row: self.row + 1,
+            column: column_name(self.column(location)),
+            field,
+            problem,
+            value: self.cell(location).to_string(),
+        }
+    }
+
+    /// Returns absolute column of the field value that is
+    /// the first non-empty cell or the first cell of the location
+    fn column(&self, location: Option<Location>) -> usize {
+        match self.find(location) {
+            Some((col, _)) => col,
+            None => location.map(|l| l.left).unwrap_or_default(),
+        }
+    }
+
+    /// Returns addresses of the cells of every located field e.g. 'L512'
+    fn addresses(&self, cols: &NamedCols) -> BTreeMap<Field, String> {
+        Field::ALL
+            .iter()
+            .filter_map(|f| {
+                let location = cols.get(*f)?;
+                let address = format!(
+                    "{}{}",
+                    column_name(self.column(Some(location))),
+                    self.row + 1
+                );
+                Some((*f, address))
+            })
+            .collect()
+    }
+
+    /// Reports the cells of the row which values
+    /// can't be read and are going to be lost
+    fn check(&self, cols: &NamedCols, sheet: &str, issues: &mut Vec<CellIssue>) {
+        let problem = match self.cell(cols.registry_number) {
+            DataType::Error(_) => Some(Problem::CellError),
+            DataType::Float(f) if f.abs() > EXCEL_MAX_EXACT_NUMBER => Some(Problem::RoundedNumber),
+            DataType::Float(f) if f.fract() != 0.0 => Some(Problem::NotANumber),
+            c if cell_registry_number(c).is_empty() => Some(Problem::MissingValue),
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            issues.push(self.issue(sheet, Field::RegistryNumber, cols.registry_number, problem));
+        }
+
+        let numbers = [
+            (Field::MaxPrice, cols.max_price),
+            (Field::ApplicationGuarantee, cols.application_guarantee),
+            (Field::ContractGuarantee, cols.contract_guarantee),
+            (Field::Estimation, cols.estimation),
+            (Field::WinnerPrice, cols.winner_price),
+        ];
+        let dates = [
+            (Field::CollectingDate, cols.collecting_date),
+            (Field::CollectingDatetime, cols.collecting_datetime),
+            (Field::ApprovalDatetime, cols.approval_datetime),
+            (Field::BiddingDatetime, cols.bidding_datetime),
+        ];
+        let texts = [
+            (Field::LotNumber, cols.lot_number),
+            (Field::PurchaseSubject, cols.purchase_subject),
+            (Field::PurchaseAbbr, cols.purchase_abbr),
+            (Field::PurchaseType, cols.purchase_type),
+            (Field::Region, cols.region),
+            (Field::CustomerType, cols.customer_type),
+            (Field::OurParticipants, cols.our_participants),
+            (Field::Etp, cols.etp),
+            (Field::Winner, cols.winner),
+            (Field::Participants, cols.participants),
+        ];
+
+        for (field, location) in numbers.iter() {
+            let problem = match self.cell(*location) {
+                DataType::Error(_) => Problem::CellError,
+                c if !c.is_empty() && cell_money(c).is_none() => Problem::NotANumber,
+                _ => continue,
+            };
+            issues.push(self.issue(sheet, *field, *location, problem));
+        }
+        for (field, location) in dates.iter() {
+            let problem = match self.cell(*location) {
+                DataType::Error(_) => Problem::CellError,
+                c if !c.is_empty() && cell_date(c).is_none() => Problem::InvalidDate,
+                _ => continue,
+            };
+            issues.push(self.issue(sheet, *field, *location, problem));
+        }
+        for (field, location) in texts.iter() {
+            if let DataType::Error(_) = self.cell(*location) {
+                issues.push(self.issue(sheet, *field, *location, Problem::CellError));
+            }
+        }
+    }
+
+    /// Returns value of the extra column keeping the type of the cell.
+    /// Dates are formated as the other dates of the record, errors are null
+    fn value(&self, location: Option<Location>, offset: Offset) -> Value {
+        match self.cell(location) {
+            DataType::String(s) => Value::from(s.as_str()),
+            DataType::Float(f) => Value::from(*f),
+            DataType::Int(i) => Value::from(*i),
+            DataType::Bool(b) => Value::from(*b),
+            DataType::DateTime(dt) => {
+                Value::from(from_excel_date(self.dates.normalize(*dt), offset))
+            }
+            _ => Value::Null,
+        }
+    }
+
+    /// Returns text of the field. Text of the multi-column
+    /// field is joined from all the non-empty cells
+    fn text(&self, location: Option<Location>) -> String {
+        self.cells(location)
+            .iter()
+            .filter_map(|c| c.get_string())
+            .filter(|s| !s.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+fn active_state_cells(
+    rng: Range<DataType>,
+    cols: NamedCols,
+    sheet: &str,
+    dates: DateSystem,
+    config: &WorkbookConfig,
+    issues: &mut Vec<CellIssue>,
+) -> Vec<Purchase> {
+    let cut_off_date = today_in_excel_date(config.timezone) - config.how_far_in_past_days as f64;
+
+    // rows of the range are relative to it's start
+    let (first_row, first_col) = rng.start().unwrap_or_default();
+    let rows = rng.rows().enumerate().map(|(i, cells)| Row {
+        cells,
+        row: first_row + i as u32,
+        first_col: first_col as usize,
+        dates,
+    });
+
+    // filter active rows and those that satisfy temporal criteria
+    let rows = rows.take(config.max_rows).filter(|r| {
+        let is_active = match r.cell(cols.status) {
+            DataType::String(s) => config.statuses.active(s).is_some(),
+            _ => false,
+        };
+        if !is_active {
+            return false;
+        }
+
+        let problem = match r.cell(cols.bidding_date) {
+            // active row is lost without the bidding date
+            DataType::Error(_) => Problem::CellError,
+            c if c.is_empty() => Problem::MissingValue,
+            _ => match local_datetime(
+                r.date(cols.bidding_date),
+                None,
+                config.offset(&r.text(cols.region)),
+            ) {
+                Some(dt) if dt > cut_off_date => {
+                    r.check(&cols, sheet, issues);
+                    return true;
+                }
+                Some(_) => return false,
+                None => Problem::InvalidDate,
+            },
+        };
+        issues.push(r.issue(sheet, Field::BiddingDate, cols.bidding_date, problem));
+        false
+    });
+
+    let mut purches: Vec<Purchase> = Vec::new();
+
+    for r in rows {
+        // the record without the number can't be told apart from the others,
+        // it's reported as missing value and isn't sent
+        let registry_number = cell_registry_number(r.cell(cols.registry_number));
+        if registry_number.is_empty() {
+            continue;
+        }
+
+        let region = r.text(cols.region);
+        let offset = config.offset(&region);
+
+        let bid_datetime = local_datetime(
+            r.date(cols.bidding_date),
+            r.date(cols.bidding_datetime),
+            offset,
+        );
+        let col_datetime = local_datetime(
+            r.date(cols.collecting_date),
+            r.date(cols.collecting_datetime),
+            offset,
+        );
+        // there is no separate time column for approval
+        let appr_datetime = local_datetime(r.date(cols.approval_datetime), None, offset);
+
+        let our_participants = r.text(cols.our_participants);
+        let participants = r.text(cols.participants);
+
+        // get the values from the cells
+        purches.push(Purchase {
+            registry_number,
+            lot_number: cell_lot_number(r.cell(cols.lot_number)),
+            purchase_subject: r.text(cols.purchase_subject),
+            purchase_abbr: r.text(cols.purchase_abbr),
+            purchase_type: r.text(cols.purchase_type),
+            region,
+            customer_type: r.text(cols.customer_type),
+            max_price: cell_money(r.cell(cols.max_price)).unwrap_or_default(),
+            application_guarantee: cell_money(r.cell(cols.application_guarantee))
+                .unwrap_or_default(),
+            contract_guarantee: cell_money(r.cell(cols.contract_guarantee)).unwrap_or_default(),
+            estimation: cell_money(r.cell(cols.estimation)).unwrap_or_default(),
+            our_participants_list: participants::parse(&our_participants),
+            our_participants,
+            etp: r.text(cols.etp),
+            winner: r.text(cols.winner),
+            winner_price: cell_money(r.cell(cols.winner_price)).unwrap_or_default(),
+            participants_list: participants::parse(&participants),
+            participants,
+            bidding_datetime: from_excel_date(bid_datetime.unwrap_or_default(), offset),
+            collecting_datetime: from_excel_date(col_datetime.unwrap_or_default(), offset),
+            approval_datetime: from_excel_date(appr_datetime.unwrap_or_default(), offset),
+            // status as it is written in config, not as it is typed in the cell
+            status: config
+                .statuses
+                .active(r.cell(cols.status).get_string().unwrap_or_default())
+                .unwrap_or_default()
+                .to_owned(),
+            path: config.path.clone(),
+            sheet: sheet.to_owned(),
+            row: r.row + 1,
+            cells: if config.cell_addresses {
+                Some(r.addresses(&cols))
+            } else {
+                None
+            },
+            // every extra column is in the map even if the sheet doesn't have it
+            extra: config
+                .extra
+                .keys()
+                .map(|k| (k.to_owned(), r.value(cols.extra.get(k).copied(), offset)))
+                .collect(),
+            changes: Vec::new(),
+        });
+    }
+    purches
+}
+
+/// Returns registry number of the cell as a digit string. It may be typed
+/// as text with '№' prefix and spaces or pasted as a number
+fn cell_registry_number(cell: &DataType) -> String {
+    match cell {
+        DataType::String(s) => s
+            .chars()
+            .filter(|c| *c != '№' && !c.is_whitespace())
+            .collect(),
+        DataType::Int(i) => i.to_string(),
+        // without exponent and fraction, e.g. 3.731e17 is '373100000000000000'
+        DataType::Float(f) if f.fract() == 0.0 => format!("{:.0}", f),
+        // a fraction can't be the number, the cell is reported by the check
+        _ => String::new(),
+    }
+}
+
+/// Returns lot number of the cell, e.g. '2' for 'Лот № 2', 'Lot 2' or 2.0
+fn cell_lot_number(cell: &DataType) -> String {
+    match cell {
+        DataType::String(s) => {
+            let s = s.trim();
+            // the prefix is compared by chars, the case of the number is kept
+            let prefix = s.char_indices().nth(3).map(|(i, _)| i).unwrap_or(s.len());
+            let s = match s[..prefix].to_lowercase().as_str() {
+                "лот" | "lot" => &s[prefix..],
+                _ => s,
+            };
+            s.trim_start_matches(|c: char| c == '№' || c.is_whitespace())
+                .to_owned()
+        }
+        c => cell_registry_number(c),
+    }
+}
+
+/// Returns amount of money of the cell. Amounts that are typed as text may use
+/// spaces as thousands separator, comma as decimal one and have currency
+fn cell_money(cell: &DataType) -> Option<Money> {
+    match cell {
+        DataType::Float(f) => Money::from_rubles(*f),
+        DataType::Int(i) => Money::from_rubles(*i as f64),
+        DataType::String(s) => Money::parse(s),
+        _ => None,
+    }
+}
+
+/// Returns excel date of the cell as it's written in the cell
+fn cell_date(cell: &DataType) -> Option<f64> {
+    cell_zoned_date(cell).map(|(dt, _)| dt)
+}
+
+/// Returns excel date of the cell. Xls and xlsb date cells are just numbers
+/// i.e. serials, OpenDocument spreadsheets keeps dates and times as text
+/// e.g. '2021-11-16T10:00:00' or 'PT10H00M00S', and people may type them
+/// as text e.g. '16.11.2021' or '10:00 МСК'. The offset is returned only
+/// if it's written in the cell, otherwise it's the local time of the workbook
+fn cell_zoned_date(cell: &DataType) -> Option<ZonedDate> {
+    match cell {
+        DataType::DateTime(dt) => Some((*dt, None)),
+        DataType::Float(f) if *f >= 0.0 => Some((*f, None)),
+        DataType::Int(i) if *i >= 0 => Some((*i as f64, None)),
+        DataType::String(s) => parse_iso_datetime(s)
+            .or_else(|| parse_iso_duration(s).map(|t| (t, None)))
+            .or_else(|| parse_ru_datetime(s)),
+        _ => None,
+    }
+}
+
+/// Returns excel date of the pair of date and time cells in the `local`
+/// offset. The moment that is written in the other offset e.g. '10:00 МСК'
+/// is converted, the offset of the time cell takes precedence
+fn local_datetime(date: Option<ZonedDate>, time: Option<ZonedDate>, local: Offset) -> Option<f64> {
+    let offset = time
+        .and_then(|(_, o)| o)
+        .or_else(|| date.and_then(|(_, o)| o));
+    let dt = cell_datetime(date.map(|(d, _)| d), time.map(|(t, _)| t))?;
+    Some(match offset {
+        Some(o) => dt + (local.seconds() - o.seconds()) as f64 / 86400.0,
+        None => dt,
+    })
+}
+
+/// Returns excel date of the pair of date and time cells.
+/// If time cell holds just a time i.e. value less than 1.0,
+/// than it's added to the date, otherwise it's taken as is
+fn cell_datetime(date: Option<f64>, time: Option<f64>) -> Option<f64> {
+    match (date, time) {
+        (_, Some(t)) if t >= 1.0 => Some(t),
+        (Some(d), Some(t)) => Some(d.trunc() + t),
+        (Some(d), None) => Some(d),
+        // time without date is not a moment
+        (None, _) => None,
+    }
+}
+
+/// Parses RFC3339 moment e.g. the time of the record from json file to excel
+/// date in the `local` offset. Time without the offset is considered local
+fn parse_iso_moment(s: &str, local: Offset) -> Option<f64> {
+    local_datetime(parse_iso_datetime(s), None, local)
+}
+
+/// Parses 'yyyy-mm-dd' date with optional 'Thh:mm:ss' time to excel date.
+/// The offset of RFC3339 time e.g. 'Z' or '+07:00' is returned separately
+fn parse_iso_datetime(s: &str) -> Option<ZonedDate> {
+    let (date, time) = match s.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (s, None),
+    };
+
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u64 = parts.next()?.parse().ok()?;
+    let day: u64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let days = simple_time::days_from_civil(year, month, day) + EXCEL_UNIX_EPOCH as i64;
+    let (time, offset) = match time {
+        Some(t) => {
+            let (t, offset) = match t.find(['Z', 'z', '+', '-']) {
+                Some(i) => (&t[..i], Some(t[i..].parse::<Offset>().ok()?)),
+                None => (t, None),
+            };
+            let mut parts = t.splitn(3, ':');
+            let hours: f64 = parts.next()?.parse().ok()?;
+            let minutes: f64 = parts.next().unwrap_or("0").parse().ok()?;
+            let seconds: f64 = parts.next().unwrap_or("0").parse().ok()?;
+            (
+                (hours * 3600.0 + minutes * 60.0 + seconds) / 86400.0,
+                offset,
+            )
+        }
+        None => (0.0, None),
+    };
+
+    Some((days as f64 + time, offset))
+}
+
+/// Parses 'dd.mm.yyyy' or 'dd.mm.yy' date with optional 'hh:mm' time
+/// or just 'hh:mm' time to excel date. Moscow time suffix is dropped
+/// e.g. '16.11.2021 10:00 (МСК)', as well as the year one e.g. '16.11.2021 г.'
+fn parse_ru_datetime(s: &str) -> Option<ZonedDate> {
+    let s = s.trim().to_lowercase();
+    let s = s.trim_end_matches(')').trim_end();
+    let msk = s.strip_suffix("мск").or_else(|| s.strip_suffix("msk"));
+    let offset = msk.map(|_| Offset::MSK);
+    let s = msk
+        .map(|s| s.trim_end().trim_end_matches('(').trim_end())
+        .map(|s| s.strip_suffix(" по").unwrap_or(s))
+        .unwrap_or(s);
+
+    let mut parts = s.split(|c: char| c.is_whitespace() || c == ',');
+    let date = parts.next()?;
+    let time = parts.find(|p| !p.is_empty() && *p != "г." && *p != "г");
+
+    if date.contains(':') && time.is_none() {
+        return parse_clock_time(date).map(|t| (t, offset));
+    }
+
+    let date = date.trim_end_matches("г.").trim_end_matches('г');
+    let mut nums = date.splitn(3, '.');
+    let day: u64 = nums.next()?.parse().ok()?;
+    let month: u64 = nums.next()?.parse().ok()?;
+    let year = nums.next()?;
+    let year: i64 = match (year.len(), year.parse().ok()?) {
+        (2, y) => 2000 + y,
+        (4, y) => y,
+        _ => return None,
+    };
+    if day == 0 || day > simple_time::days_in_month(year, month) {
+        return None;
+    }
+
+    let days = simple_time::days_from_civil(year, month, day) + EXCEL_UNIX_EPOCH as i64;
+    let time = match time {
+        Some(t) => parse_clock_time(t)?,
+        None => 0.0,
+    };
+
+    Some((days as f64 + time, offset))
+}
+
+/// Parses 'hh:mm' or 'hh:mm:ss' time to a fraction of the day
+fn parse_clock_time(s: &str) -> Option<f64> {
+    let mut parts = s.splitn(3, ':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: u64 = parts.next().unwrap_or("0").parse().ok()?;
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+
+    Some((hours * 3600 + minutes * 60 + seconds) as f64 / 86400.0)
+}
+
+/// Parses 'PThhHmmMssS' duration to a fraction of the day
+fn parse_iso_duration(s: &str) -> Option<f64> {
+    let mut rest = s.strip_prefix("PT")?;
+    let mut seconds = 0.0;
+
+    for (unit, multi) in [('H', 3600.0), ('M', 60.0), ('S', 1.0)].iter() {
+        if let Some((n, r)) = rest.split_once(*unit) {
+            seconds += n.parse::<f64>().ok()? * multi;
+            rest = r;
+        }
+    }
+
+    if !rest.is_empty() {
+        return None;
+    }
+    Some(seconds / 86400.0)
+}
+
+/// Converts an excel date which is just a float number
+/// to formated time string as in RFS3339. Excel date has no time zone,
+/// it's the local time of the `offset`
+fn from_excel_date(excel_date: f64, offset: Offset) -> String {
+    let days = excel_date - EXCEL_UNIX_EPOCH as f64;
+    let seconds = seconds_from_days(days);
+    let mut moment = Moment::from_seconds_since_epoch(seconds);
+    moment.offset = offset;
+    moment.to_string()
+}
+
+/// Returns today's date of the time zone in excel representation
+/// which is a float number
+fn today_in_excel_date(offset: Offset) -> f64 {
+    if let Ok(duration) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        let local_secs = duration.as_secs() as i64 + offset.seconds();
+        let d_since_epoch = days_from_seconds(local_secs.max(0) as u64);
+        let today_excel = d_since_epoch + EXCEL_UNIX_EPOCH;
+        today_excel as f64
+    } else {
+        0.0
+    }
+}
+
+const fn days_from_seconds(timestamp_secs: u64) -> u64 {
+    timestamp_secs / 86400
+}
+
+/// Dates before 1970 give negative seconds
+fn seconds_from_days(days: f64) -> i64 {
+    (days * 86400.0).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::{fs, io::Write};
+
+    #[test]
+    fn test_print_active_state() {
+        let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
+        assert!(print_active_state(Path::new(&wb_path), &WorkbookConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_print_active_state_json() {
+        let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
+        assert!(print_active_state_json(Path::new(&wb_path), &WorkbookConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_active_state_json() {
+        let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
+        assert!(active_state_json(Path::new(&wb_path), &WorkbookConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_active_state() {
+        let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
+        assert!(active_state(Path::new(&wb_path), &WorkbookConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_check_columns() {
+        let config = WorkbookConfig::default();
+        // defined names of the workbook with every column of the schema
+        let defined_names: Vec<(String, String)> = Field::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let col = column_name(i);
+                let reference = format!("Реестр!${}$2:${}$100", col, col);
+                (config.schema.names(*f)[0].clone(), reference)
+            })
+            .collect();
+        let check = |defined_names: &[(String, String)]| {
+            let named_ranges: Vec<NamedRange> = defined_names
+                .iter()
+                .filter_map(|name| Some(NamedRange::new(config.schema.field(&name.0)?, name)))
+                .collect();
+            let sheet = SheetColumns::new(
+                "Реестр".to_owned(),
+                Some(Range::empty()),
+                &named_ranges,
+                &[],
+                &config,
+            );
+            sheet.check
+        };
+        assert!(check(&defined_names).is_ok());
+
+        // required column is missing and the other one is malformed
+        let mut defined_names = defined_names;
+        defined_names.retain(|(name, _)| config.schema.field(name) != Some(Field::Status));
+        defined_names[0].1 = "Реестр!$1:$1".to_owned();
+        let check = check(&defined_names);
+        assert!(check.is_broken());
+        assert_eq!(check.missing, [Field::Status]);
+        assert_eq!(
+            check.invalid,
+            [(Field::RegistryNumber, "Реестр!$1:$1".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_active_state_cells() {
+        let s = |s: &str| DataType::String(s.to_owned());
+        // far in the future to be active
+        let date = DataType::DateTime(73051.5);
+        let rows = [
+            [s("№ 0373100000121000001"), s("идем"), date.clone()],
+            [DataType::Empty, s("идем"), date.clone()],
+            [s(" № "), s("идем"), date.clone()],
+            [s("0373100000121000002"), s("не идем"), date],
+        ];
+        let mut rng = Range::new((4, 0), (7, 2));
+        for (i, row) in rows.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                rng.set_value((4 + i as u32, j as u32), cell.clone());
+            }
+        }
+        let cols = NamedCols {
+            registry_number: Some(Location::column(0)),
+            status: Some(Location::column(1)),
+            bidding_date: Some(Location::column(2)),
+            ..NamedCols::default()
+        };
+
+        let mut issues = Vec::new();
+        let config = WorkbookConfig::default();
+        let purches =
+            active_state_cells(rng, cols, "", DateSystem::Excel1900, &config, &mut issues);
+        // records without the registry number are reported, not sent
+        let numbers: Vec<(&str, u32)> = purches
+            .iter()
+            .map(|p| (p.registry_number.as_str(), p.row))
+            .collect();
+        assert_eq!(numbers, [("0373100000121000001", 5)]);
+        let issues: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            issues,
+            [
+                "cell A6 (registry_number): missing value ''",
+                "cell A7 (registry_number): missing value ' № '",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_msk_in_other_region() {
+        let s = |s: &str| DataType::String(s.to_owned());
+        let rows = [
+            [s("10.03.2099 10:00 (МСК)"), DataType::Empty],
+            [s("10.03.2099 10:00"), DataType::Empty],
+            [s("10.03.2099"), s("22:00 по МСК")],
+            [s("2099-03-10T10:00:00+03:00"), DataType::Empty],
+        ];
+        let mut rng = Range::new((1, 0), (rows.len() as u32, 4));
+        for (i, [date, time]) in rows.iter().enumerate() {
+            let row = i as u32 + 1;
+            rng.set_value((row, 0), s(&format!("037310000012100000{}", i)));
+            rng.set_value((row, 1), s("идем"));
+            rng.set_value((row, 2), date.clone());
+            rng.set_value((row, 3), time.clone());
+            rng.set_value((row, 4), s("Новосибирская область"));
+        }
+        let cols = NamedCols {
+            registry_number: Some(Location::column(0)),
+            status: Some(Location::column(1)),
+            bidding_date: Some(Location::column(2)),
+            bidding_datetime: Some(Location::column(3)),
+            region: Some(Location::column(4)),
+            ..NamedCols::default()
+        };
+        // МСК+4
+        let config: WorkbookConfig =
+            toml::from_str(r#"region_timezones = { "Новосибирская область" = "+07:00" }"#).unwrap();
+
+        let purches = active_state_cells(
+            rng,
+            cols,
+            "",
+            DateSystem::Excel1900,
+            &config,
+            &mut Vec::new(),
+        );
+        let dates: Vec<&str> = purches
+            .iter()
+            .map(|p| p.bidding_datetime.as_str())
+            .collect();
+        assert_eq!(
+            dates,
+            [
+                "2099-03-10T14:00:00+07:00",
+                "2099-03-10T10:00:00+07:00",
+                "2099-03-11T02:00:00+07:00",
+                "2099-03-10T14:00:00+07:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merged_purchases() {
+        let s = |s: &str| DataType::String(s.to_owned());
+        // far in the future to be active
+        let date = DataType::DateTime(73051.5);
+        let range = |rows: &[(&str, &str)]| {
+            let mut rng = Range::new((1, 0), (rows.len() as u32, 3));
+            for (i, (num, winner)) in rows.iter().enumerate() {
+                let row = i as u32 + 1;
+                rng.set_value((row, 0), s(num));
+                rng.set_value((row, 1), s("идем"));
+                rng.set_value((row, 2), date.clone());
+                rng.set_value((row, 3), s(winner));
+            }
+            rng
+        };
+        let named = |field: Field, name: &str, reference: &str| {
+            NamedRange::new(field, &(name.to_owned(), reference.to_owned()))
+        };
+        let sheet_named = |sheet: &str, winner: bool| {
+            let mut named_ranges = vec![
+                named(
+                    Field::RegistryNumber,
+                    "Номер",
+                    &format!("{}!$A$2:$A$100", sheet),
+                ),
+                named(Field::Status, "Статус", &format!("{}!$B$2:$B$100", sheet)),
+                named(
+                    Field::BiddingDate,
+                    "Дата_проведения_аукциона_конкурса",
+                    &format!("{}!$C$2:$C$100", sheet),
+                ),
+            ];
+            if winner {
+                named_ranges.push(named(
+                    Field::Winner,
+                    "Победитель",
+                    &format!("{}!$D$2:$D$100", sheet),
+                ));
+            }
+            named_ranges
+        };
+        let config = WorkbookConfig::default();
+
+        let sheets = || {
+            vec![
+                SheetColumns::new(
+                    "Архив".to_owned(),
+                    Some(range(&[("0373100000121000001", "ООО Ромашка")])),
+                    &sheet_named("Архив", false),
+                    &[],
+                    &config,
+                ),
+                SheetColumns::new(
+                    "Реестр".to_owned(),
+                    Some(range(&[
+                        ("0373100000121000002", "ИП Иванов"),
+                        ("0373100000121000003", ""),
+                    ])),
+                    &sheet_named("Реестр", true),
+                    &[],
+                    &config,
+                ),
+            ]
+        };
+
+        // columns are checked sheet by sheet
+        let checks: Vec<(String, bool)> = sheets()
+            .into_iter()
+            .map(|s| (s.check.sheet, s.check.missing.contains(&Field::Winner)))
+            .collect();
+        assert_eq!(
+            checks,
+            [("Архив".to_owned(), true), ("Реестр".to_owned(), false)]
+        );
+
+        let purches = merged_purchases(sheets(), DateSystem::Excel1900, &config, &mut Vec::new())
+            .unwrap()
+            .unwrap();
+        let records: Vec<(&str, &str, u32, &str)> = purches
+            .iter()
+            .map(|p| {
+                (
+                    p.registry_number.as_str(),
+                    p.sheet.as_str(),
+                    p.row,
+                    p.winner.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            records,
+            [
+                ("0373100000121000001", "Архив", 2, ""),
+                ("0373100000121000002", "Реестр", 2, "ИП Иванов"),
+                ("0373100000121000003", "Реестр", 3, ""),
+            ]
+        );
+
+        // the sheet that can't be read fails the whole workbook
+        let mut broken = sheets();
+        broken.push(SheetColumns::new(
+            "Итоги".to_owned(),
+            None,
+            &sheet_named("Итоги", true),
+            &[],
+            &config,
+        ));
+        match merged_purchases(broken, DateSystem::Excel1900, &config, &mut Vec::new()) {
+            Err(WorkbookError::SchemaError(checks)) => {
+                let sheets: Vec<&str> = checks.iter().map(|c| c.sheet.as_str()).collect();
+                assert_eq!(sheets, ["Итоги"]);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_header_cols() {
+        let mut rng = Range::new((0, 0), (2, 3));
+        rng.set_value((1, 0), DataType::String("Номер".to_owned()));
+        rng.set_value((1, 1), DataType::String(" статус ".to_owned()));
+        rng.set_value((1, 2), DataType::Float(1.0));
+        rng.set_value((1, 3), DataType::String("Номер".to_owned()));
+
+        let schema = Schema::default();
+        let cols = header_cols(&rng, 2, |s| schema.field(s));
+        assert_eq!(cols.len(), 2);
+        assert_eq!(cols[&Field::RegistryNumber].left, 0);
+        assert_eq!(cols[&Field::Status].left, 1);
+        // header row itself is not covered
+        assert!(!cols[&Field::Status].contains_row(1));
+        assert!(cols[&Field::Status].contains_row(2));
+
+        assert!(header_cols(&rng, 1, |s| schema.field(s)).is_empty());
+    }
+
+    #[test]
+    fn test_columns_check() {
+        let config = WorkbookConfig::default();
+        let mut field_cols: HashMap<Field, Location> = Field::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (*f, Location::column(i)))
+            .collect();
+
+        let check = ColumnsCheck::new("", &[], &field_cols, &config);
+        assert!(check.is_ok());
+        assert!(!check.is_broken());
+
+        // optional field is missing
+        field_cols.remove(&Field::Winner);
+        let check = ColumnsCheck::new("", &[], &field_cols, &config);
+        assert!(!check.is_ok());
+        assert!(!check.is_broken());
+        assert_eq!(check.to_string(), "missing optional [winner]");
+
+        // required field is missing and named range is malformed
+        field_cols.remove(&Field::Status);
+        let nr = NamedRange::new(
+            Field::RegistryNumber,
+            &("Номер".to_owned(), "Реестр!$1:$1".to_owned()),
+        );
+        field_cols.remove(&Field::RegistryNumber);
+        let check = ColumnsCheck::new("Реестр", &[nr], &field_cols, &config);
+        assert!(check.is_broken());
+        assert_eq!(
+            check.to_string(),
+            "sheet 'Реестр': missing required [status]; missing optional [winner]; \
+             invalid [registry_number = 'Реестр!$1:$1']"
+        );
+    }
+
+    #[test]
+    fn test_named_range_reference() {
+        let def_name = |r: &str| ("Номер".to_owned(), r.to_owned());
+
+        let nr = NamedRange::new(Field::RegistryNumber, &def_name("Реестр!$B:$B"));
+        assert_eq!((nr.sheet.as_str(), nr.range.as_str()), ("Реестр", "$B:$B"));
+
+        let nr = NamedRange::new(Field::RegistryNumber, &def_name("'Реестр 2021'!$B:$B"));
+        assert_eq!(nr.sheet, "Реестр 2021");
+
+        // OpenDocument reference
+        let nr = NamedRange::new(Field::RegistryNumber, &def_name("$Реестр.$B$1:.$B$100"));
+        assert_eq!(
+            (nr.sheet.as_str(), nr.range.as_str()),
+            ("Реестр", "$B$1:$B$100")
+        );
+        assert_eq!(nr.col_num(ColumnPosition::Left).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_named_range_location() {
+        let location = |r: &str| {
+            NamedRange::new(Field::Participants, &("Участники".to_owned(), r.to_owned())).location()
+        };
+
+        assert_eq!(location("Реестр!$B:$B").unwrap(), Location::column(1));
+        assert_eq!(
+            location("Реестр!$B$5:$D$900").unwrap(),
+            Location {
+                left: 1,
+                right: 3,
+                rows: Some((4, 899))
+            }
+        );
+        assert_eq!(location("Реестр!$AA$2").unwrap().rows, Some((1, 1)));
+        assert!(location("Реестр!$D$5:$B$900").is_err());
+        assert!(location("Реестр!$B$900:$B$5").is_err());
+        assert!(location("Реестр!$B$0:$B$5").is_err());
+    }
+
+    #[test]
+    fn test_row_cells() {
+        let s = |s: &str| DataType::String(s.to_owned());
+        let cells = [s("0373"), s("ООО Ромашка"), DataType::Empty, s("ИП Иванов")];
+        // range starts from column B and the row is the 5th one
+        let row = Row {
+            cells: &cells,
+            row: 4,
+            first_col: 1,
+            dates: DateSystem::default(),
+        };
+
+        let participants = Location {
+            left: 2,
+            right: 4,
+            rows: Some((4, 899)),
+        };
+        assert_eq!(row.text(Some(participants)), "ООО Ромашка; ИП Иванов");
+        assert_eq!(row.cell(Some(participants)), &s("ООО Ромашка"));
+        assert_eq!(row.cell(Some(Location::column(1))), &s("0373"));
+        assert_eq!(row.cell(Some(Location::column(0))), &DataType::Empty);
+        assert_eq!(row.cell(Some(Location::column(9))), &DataType::Empty);
+        assert_eq!(row.cell(None), &DataType::Empty);
+
+        // the row is out of bounds
+        let header = Location {
+            rows: Some((0, 3)),
+            ..Location::column(1)
+        };
+        assert_eq!(row.cell(Some(header)), &DataType::Empty);
+
+        let cols = NamedCols {
+            registry_number: Some(Location::column(1)),
+            participants: Some(participants),
+            winner: Some(Location::column(3)),
+            ..NamedCols::default()
+        };
+        let addresses: Vec<(Field, String)> = row.addresses(&cols).into_iter().collect();
+        assert_eq!(
+            addresses,
+            [
+                (Field::RegistryNumber, "B5".to_owned()),
+                (Field::Winner, "D5".to_owned()),
+                (Field::Participants, "C5".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_row_value() {
+        let cells = [
+            DataType::String("Иванов".to_owned()),
+            DataType::Float(12.5),
+            DataType::Int(3),
+            DataType::Bool(true),
+            DataType::DateTime(44516.5),
+            DataType::Error(calamine::CellErrorType::NA),
+        ];
+        let row = Row {
+            cells: &cells,
+            row: 4,
+            first_col: 0,
+            dates: DateSystem::Excel1900,
+        };
+        let values: Vec<Value> = (0..=cells.len())
+            .map(|i| row.value(Some(Location::column(i)), Offset::MSK))
+            .collect();
+        assert_eq!(
+            serde_json::to_string(&values).unwrap(),
+            r#"["Иванов",12.5,3,true,"2021-11-16T12:00:00+03:00",null,null]"#
+        );
+    }
+
+    #[test]
+    fn test_row_date() {
+        // the same moment as it's read from the cell of every format
+        let cells = [
+            // xlsx
+            DataType::DateTime(44516.5),
+            // xls and xlsb
+            DataType::Float(44516.5),
+            DataType::Int(44516),
+            // ods
+            DataType::String("2021-11-16T12:00:00".to_owned()),
+            DataType::String("16.11.2021 12:00".to_owned()),
+        ];
+        let row = Row {
+            cells: &cells,
+            row: 4,
+            first_col: 0,
+            dates: DateSystem::Excel1900,
+        };
+        let dates: Vec<Option<f64>> = (0..cells.len())
+            .map(|i| row.date(Some(Location::column(i))).map(|(dt, _)| dt))
+            .collect();
+        assert_eq!(
+            dates,
+            [
+                Some(44516.5),
+                Some(44516.5),
+                Some(44516.0),
+                Some(44516.5),
+                Some(44516.5)
+            ]
+        );
+
+        // numbers aren't reported as invalid dates
+        let cols = NamedCols {
+            approval_datetime: Some(Location::column(1)),
+            ..NamedCols::default()
+        };
+        let mut issues = Vec::new();
+        row.check(&cols, "Реестр", &mut issues);
+        assert!(issues.iter().all(|i| i.field != Field::ApprovalDatetime));
+    }
+
+    #[test]
+    fn test_column_name() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(column_name(702), "AAA");
+    }
+
+    #[test]
+    fn test_row_check() {
+        let s = |s: &str| DataType::String(s.to_owned());
+        let cells = [
+            DataType::Empty,
+            s("1 234,5"),
+            s("12З4"),
+            DataType::Error(calamine::CellErrorType::Ref),
+            s("16.13.2021"),
+        ];
+        let row = Row {
+            cells: &cells,
+            row: 4,
+            first_col: 0,
+            dates: DateSystem::Excel1900,
+        };
+        let cols = NamedCols {
+            registry_number: Some(Location::column(0)),
+            max_price: Some(Location::column(1)),
+            estimation: Some(Location::column(2)),
+            region: Some(Location::column(3)),
+            approval_datetime: Some(Location::column(4)),
+            ..NamedCols::default()
+        };
+
+        let mut issues = Vec::new();
+        row.check(&cols, "Реестр", &mut issues);
+        let issues: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            issues,
+            [
+                "sheet 'Реестр' cell A5 (registry_number): missing value ''",
+                "sheet 'Реестр' cell C5 (estimation): not a number '12З4'",
+                "sheet 'Реестр' cell E5 (approval_datetime): invalid date '16.13.2021'",
+                "sheet 'Реестр' cell D5 (region): cell error '#REF!'",
+            ]
+        );
+
+        let cells = [DataType::Float(373100000121000001.0)];
+        let row = Row {
+            cells: &cells,
+            row: 5,
+            first_col: 0,
+            dates: DateSystem::Excel1900,
+        };
+        let mut issues = Vec::new();
+        row.check(&cols, "Реестр", &mut issues);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].problem, Problem::RoundedNumber);
+        assert_eq!(issues[0].value, "373100000121000000");
+
+        let cells = [DataType::Float(3.2e-5)];
+        let row = Row {
+            cells: &cells,
+            row: 5,
+            first_col: 0,
+            dates: DateSystem::Excel1900,
+        };
+        let mut issues = Vec::new();
+        row.check(&cols, "Реестр", &mut issues);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].problem, Problem::NotANumber);
+        assert_eq!(issues[0].field, Field::RegistryNumber);
+    }
+
+    #[test]
+    fn test_dedup() {
+        let p = |num: &str, row: u32| Purchase {
+            registry_number: num.to_owned(),
+            sheet: "Реестр".to_owned(),
+            row,
+            ..Purchase::default()
+        };
+        let purches = || vec![p("1", 2), p("2", 3), p("1", 4), p("3", 5)];
+        let rows = |purches: Vec<Purchase>| purches.iter().map(|p| p.row).collect::<Vec<_>>();
+
+        let result = dedup(purches(), Duplicates::FirstWins).unwrap();
+        assert_eq!(rows(result), [2, 3, 5]);
+
+        let result = dedup(purches(), Duplicates::LastWins).unwrap();
+        assert_eq!(rows(result), [4, 3, 5]);
+
+        match dedup(purches(), Duplicates::Reject) {
+            Err(WorkbookError::DuplicateError(d)) => assert_eq!(
+                d[0].to_string(),
+                "registry number '1' is in sheet 'Реестр' row 2 and sheet 'Реестр' row 4"
+            ),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_active_state_compared() {
+        let p = |lot: &str, region: &str| Purchase {
+            registry_number: "0373100000121000001".to_owned(),
+            lot_number: lot.to_owned(),
+            region: region.to_owned(),
+            status: "идем".to_owned(),
+            ..Purchase::default()
+        };
+        let compared = |old: &str, new: &[Purchase], config: &WorkbookConfig| {
+            active_state_compared(serde_json::from_str(old).unwrap(), new, config)
+        };
+        let to_json = |purches: &[Purchase]| serde_json::to_string(purches).unwrap();
+
+        let old = to_json(&[p("1", "Москва"), p("2", "Москва"), p("3", "Москва")]);
+        let new = [p("1", "Москва"), p("2", "Тверь")];
+
+        let result = compared(&old, &new, &WorkbookConfig::default()).unwrap();
+        let lots: Vec<(&str, &str)> = result
+            .iter()
+            .map(|p| (p.lot_number.as_str(), p.status.as_str()))
+            .collect();
+        assert_eq!(lots, [("2", "идем"), ("3", "не идем")]);
+        let change = |field: &str, old: &str, new: &str| {
+            FieldChange::new(field, Value::from(old), Value::from(new))
+        };
+        assert_eq!(result[0].changes, [change("region", "Москва", "Тверь")]);
+        assert_eq!(result[1].changes, [change("status", "идем", "не идем")]);
+
+        // old snapshots have no lot numbers
+        let old = r#"[{"registry_number":"0373100000121000001","purchase_subject":"","purchase_abbr":"","purchase_type":"","collecting_datetime":"","approval_datetime":"","bidding_datetime":"","region":"Москва","customer_type":"","max_price":0.0,"application_guarantee":0.0,"contract_guarantee":0.0,"status":"идем","our_participants":"","estimation":0.0,"etp":"","winner":"","winner_price":0.0,"participants":""}]"#;
+        let new = [p("", "Москва")];
+        assert!(compared(old, &new, &WorkbookConfig::default()).is_none());
+
+        // a kopeck is a change, but float noise isn't
+        let priced = |kopecks: i64| Purchase {
+            winner_price: Money::from_kopecks(kopecks),
+            ..p("1", "Москва")
+        };
+        let old = to_json(&[priced(123456789)]);
+        assert!(compared(&old, &[priced(123456790)], &WorkbookConfig::default()).is_some());
+        // every amount is compared
+        let old = to_json(&[p("1", "Москва")]);
+        let new = Purchase {
+            max_price: Money::from_kopecks(1),
+            ..p("1", "Москва")
+        };
+        let result = compared(&old, &[new], &WorkbookConfig::default()).unwrap();
+        assert_eq!(
+            result[0].changes,
+            [FieldChange::new(
+                "max_price",
+                Value::from(0.0),
+                Value::from(0.01)
+            )]
+        );
+        let old = to_json(&[priced(123456789)]);
+        let new = Purchase {
+            winner_price: Money::from_rubles(1234567.88 + 0.01).unwrap(),
+            ..p("1", "Москва")
+        };
+        assert!(compared(&old, &[new], &WorkbookConfig::default()).is_none());
+
+        // extra columns are compared only if it's set
+        let noted = |manager: &str, notes: &str| Purchase {
+            extra: [("manager", manager), ("notes", notes)]
+                .iter()
+                .map(|(k, v)| (k.to_string(), Value::from(*v)))
+                .collect(),
+            ..p("1", "Москва")
+        };
+        let old = to_json(&[noted("Иванов", "звонили")]);
+        let config = WorkbookConfig {
+            extra: toml::from_str(
+                r#"
+                manager = { names = ["Ответственный"], compare = true }
+                notes = { names = ["Примечание"] }
+            "#,
+            )
+            .unwrap(),
+            ..WorkbookConfig::default()
+        };
+        let changed =
+            |new: Purchase, config: &WorkbookConfig| compared(&old, &[new], config).is_some();
+        assert!(!changed(noted("Иванов", "не звонили"), &config));
+        assert!(changed(noted("Петров", "звонили"), &config));
+        assert!(!changed(
+            noted("Петров", "звонили"),
+            &WorkbookConfig::default()
+        ));
+    }
+
+    #[test]
+    fn test_compared_without_registry_number() {
+        let config = WorkbookConfig::default();
+        let header = |f: Field| config.headers.names(f)[0].clone();
+        // csv table where two rows have no registry number
+        let rows = [
+            [
+                header(Field::RegistryNumber),
+                header(Field::Status),
+                header(Field::BiddingDate),
+            ],
+            [
+                "0373100000121000001".to_owned(),
+                "идем".to_owned(),
+                "20.10.2099".to_owned(),
+            ],
+            [String::new(), "идем".to_owned(), "22.10.2099".to_owned()],
+            ["№".to_owned(), "идем".to_owned(), "23.10.2099".to_owned()],
+        ];
+        let mut rng = Range::new((0, 0), (3, 2));
+        for (i, row) in rows.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                rng.set_value((i as u32, j as u32), DataType::String(cell.clone()));
+            }
+        }
+
+        let (purches, issues) = active_state_range(rng, &config).unwrap();
+        let purches = purches.unwrap();
+        assert_eq!(purches.len(), 1);
+        assert_eq!(issues.len(), 2);
+
+        // snapshot and diff of the unchanged table
+        let old = serde_json::from_str(&serde_json::to_string(&purches).unwrap()).unwrap();
+        assert!(active_state_compared(old, &purches, &config).is_none());
+
+        // old snapshot that has records without the number
+        let empty = |bidding: &str| Purchase {
+            bidding_datetime: bidding.to_owned(),
+            status: "идем".to_owned(),
+            ..Purchase::default()
+        };
+        let mut old = purches.clone();
+        old.push(empty("2099-10-23T00:00:00+03:00"));
+        old.push(empty("2099-10-22T00:00:00+03:00"));
+        assert!(active_state_compared(old, &purches, &config).is_none());
+        let new = [purches[0].clone(), empty("2099-10-22T00:00:00+03:00")];
+        assert!(active_state_compared(purches.clone(), &new, &config).is_none());
+    }
+
+    #[test]
+    fn test_field_changes() {
+        let p = |bidding: &str, price: i64, manager: &str| Purchase {
+            bidding_datetime: bidding.to_owned(),
+            winner_price: Money::from_kopecks(price),
+            purchase_subject: "поставка бумаги".to_owned(),
+            extra: [("manager".to_owned(), Value::from(manager))]
+                .iter()
+                .cloned()
+                .collect(),
+            ..Purchase::default()
+        };
+        let extras: Extras =
+            toml::from_str(r#"manager = { names = ["Ответственный"], compare = true }"#).unwrap();
+        let old = p("2021-11-16T10:00:00+03:00", 100, "Иванов");
+
+        assert!(old.field_changes(&old, &extras).is_empty());
+        assert_eq!(
+            old.field_changes(&p("2021-11-16T12:00:00+03:00", 150, "Петров"), &extras),
+            [
+                FieldChange::new(
+                    "bidding_datetime",
+                    Value::from("2021-11-16T10:00:00+03:00"),
+                    Value::from("2021-11-16T12:00:00+03:00")
+                ),
+                FieldChange::new("winner_price", Value::from(1.0), Value::from(1.5)),
+                FieldChange::new(
+                    "extra.manager",
+                    Value::from("Иванов"),
+                    Value::from("Петров")
+                ),
+            ]
+        );
+        // every data field is compared
+        let edited = Purchase {
+            purchase_subject: "поставка картона".to_owned(),
+            etp: "РТС-тендер".to_owned(),
+            ..old.clone()
+        };
+        let fields: Vec<String> = old
+            .field_changes(&edited, &extras)
+            .into_iter()
+            .map(|c| c.field)
+            .collect();
+        assert_eq!(fields, ["purchase_subject", "etp"]);
+
+        // neither where the record is read from
+        // nor extras without the flag are compared
+        let new = Purchase {
+            sheet: "Архив".to_owned(),
+            row: 512,
+            ..p("2021-11-16T10:00:00+03:00", 100, "Петров")
+        };
+        assert!(old.field_changes(&new, &Extras::default()).is_empty());
+
+        let json = serde_json::to_value(Purchase {
+            changes: old.field_changes(&new, &extras),
+            ..new
+        })
+        .unwrap();
+        assert_eq!(
+            json["changes"],
+            serde_json::json!([{"field": "extra.manager", "old": "Иванов", "new": "Петров"}])
+        );
+        assert!(serde_json::to_value(&old).unwrap().get("changes").is_none());
+    }
+
+    #[test]
+    fn test_cell_money() {
+        let s = |s: &str| DataType::String(s.to_owned());
+        let m = |kopecks: i64| Some(Money::from_kopecks(kopecks));
+        assert_eq!(cell_money(&DataType::Float(1234567.89)), m(123456789));
+        assert_eq!(cell_money(&DataType::Int(1250000)), m(125000000));
+        assert_eq!(cell_money(&s("1 234 567,89 руб.")), m(123456789));
+        assert_eq!(cell_money(&s("12З4")), None);
+        assert_eq!(cell_money(&DataType::Empty), None);
+    }
+
+    #[test]
+    fn test_cell_registry_number() {
+        let s = |s: &str| DataType::String(s.to_owned());
+
+        assert_eq!(
+            cell_registry_number(&s("№0373100000121000001")),
+            "0373100000121000001"
+        );
+        assert_eq!(
+            cell_registry_number(&s(" № 0373100000121000001 ")),
+            "0373100000121000001"
+        );
+        assert_eq!(
+            cell_registry_number(&DataType::Int(32110775841)),
+            "32110775841"
+        );
+        assert_eq!(
+            cell_registry_number(&DataType::Float(32110775841.0)),
+            "32110775841"
+        );
+        assert_eq!(
+            cell_registry_number(&DataType::Float(373100000121000001.0)),
+            "373100000121000000"
+        );
+        assert_eq!(cell_registry_number(&DataType::Float(1.5)), "");
+        assert_eq!(cell_registry_number(&DataType::Float(3.2e-5)), "");
+        assert_eq!(cell_registry_number(&DataType::Empty), "");
+        assert_eq!(cell_registry_number(&DataType::Bool(true)), "");
+    }
+
+    #[test]
+    fn test_cell_lot_number() {
+        let s = |s: &str| DataType::String(s.to_owned());
+
+        assert_eq!(cell_lot_number(&s("Лот № 2")), "2");
+        assert_eq!(cell_lot_number(&s("лот 12")), "12");
+        assert_eq!(cell_lot_number(&s(" №3")), "3");
+        assert_eq!(cell_lot_number(&s("4")), "4");
+        assert_eq!(cell_lot_number(&DataType::Float(5.0)), "5");
+        assert_eq!(cell_lot_number(&DataType::Int(6)), "6");
+        assert_eq!(cell_lot_number(&s("Lot № 7")), "7");
+        assert_eq!(cell_lot_number(&s("lot 8")), "8");
+        assert_eq!(cell_lot_number(&s("ЛОТ№9")), "9");
+        assert_eq!(cell_lot_number(&s("Лот 2А")), "2А");
+        assert_eq!(cell_lot_number(&s("Л")), "Л");
+        assert_eq!(cell_lot_number(&DataType::Empty), "");
+    }
+
+    #[test]
+    fn test_cell_date() {
+        let s = |s: &str| DataType::String(s.to_owned());
+
+        assert_eq!(cell_date(&DataType::DateTime(44516.5)), Some(44516.5));
+        assert_eq!(cell_date(&s("2021-11-16")), Some(44516.0));
+        assert_eq!(cell_date(&s("2021-11-16T12:00:00")), Some(44516.5));
+        assert_eq!(cell_date(&s("PT12H00M00S")), Some(0.5));
+        assert_eq!(cell_date(&s("2021-13-16")), None);
+        assert_eq!(cell_date(&s("16.11.2021")), Some(44516.0));
+        assert_eq!(cell_date(&s(" 16.11.21 ")), Some(44516.0));
+        assert_eq!(cell_date(&s("16.11.2021 12:00")), Some(44516.5));
+        assert_eq!(cell_date(&s("16.11.2021г., 12:00 (МСК)")), Some(44516.5));
+        assert_eq!(cell_date(&s("12:00 МСК")), Some(0.5));
+        assert_eq!(cell_date(&s("12:00 по мск")), Some(0.5));
+        assert_eq!(cell_date(&s("18:00:00")), Some(0.75));
+        assert_eq!(cell_date(&s("29.02.2020")), Some(43890.0));
+        assert_eq!(cell_date(&s("29.02.2021")), None);
+        assert_eq!(cell_date(&s("16.11.2021 25:00")), None);
+        assert_eq!(cell_date(&s("16.11")), None);
+        assert_eq!(cell_date(&s("16.11.2021 12")), None);
+        assert_eq!(cell_date(&s("идем")), None);
+        assert_eq!(cell_date(&DataType::Float(44516.5)), Some(44516.5));
+        assert_eq!(cell_date(&DataType::Int(44516)), Some(44516.0));
+        assert_eq!(cell_date(&DataType::Float(-1.0)), None);
+        assert_eq!(cell_date(&DataType::Bool(true)), None);
+    }
+
+    #[test]
+    fn test_parse_iso_moment() {
+        // 16.11.2021 10:00 in Moscow
+        let msk = 44516.0 + 10.0 / 24.0;
+        let moment = |s: &str, local: Offset| {
+            parse_iso_moment(s, local).map(|dt| (dt * 86400.0).round() / 86400.0)
+        };
+        assert_eq!(moment("2021-11-16T07:00:00Z", Offset::MSK), Some(msk));
+        assert_eq!(moment("2021-11-16T10:00:00+03:00", Offset::MSK), Some(msk));
+        assert_eq!(moment("2021-11-16T14:00:00+07:00", Offset::MSK), Some(msk));
+        assert_eq!(
+            moment("2021-11-16T07:00:00+00:00", Offset::UTC),
+            Some(44516.0 + 7.0 / 24.0)
+        );
+        // the date is changed by the offset
+        assert_eq!(
+            moment("2021-11-17T02:00:00+07:00", Offset::MSK),
+            Some(44516.0 + 22.0 / 24.0)
+        );
+        // local time
+        assert_eq!(moment("2021-11-16T10:00:00", Offset::MSK), Some(msk));
+        assert_eq!(moment("2021-11-16", Offset::MSK), Some(44516.0));
+        assert_eq!(moment("2021-11-16T10:00:00+3x", Offset::MSK), None);
+    }
+
+    #[test]
+    fn test_cell_datetime() {
+        let s = |s: &str| DataType::String(s.to_owned());
+        let dt = |d: &DataType, t: &DataType| cell_datetime(cell_date(d), cell_date(t));
+        let date = DataType::DateTime(44516.0);
+
+        assert_eq!(dt(&date, &DataType::DateTime(0.4375)), Some(44516.4375));
+        assert_eq!(dt(&date, &s("10:30 МСК")), Some(44516.4375));
+        assert_eq!(dt(&s("16.11.2021"), &s("12:00")), Some(44516.5));
+        assert_eq!(
+            dt(&DataType::DateTime(44516.25), &s("12:00")),
+            Some(44516.5)
+        );
+        assert_eq!(dt(&date, &DataType::DateTime(44517.5)), Some(44517.5));
+        assert_eq!(dt(&date, &DataType::Empty), Some(44516.0));
+        assert_eq!(dt(&DataType::Empty, &s("12:00")), None);
+    }
+
+    #[test]
+    fn test_date_system() {
+        assert_eq!(DateSystem::Excel1900.normalize(44516.5), 44516.5);
+        assert_eq!(DateSystem::Excel1900.normalize(61.0), 61.0);
+        // 28.02.1900
+        assert_eq!(DateSystem::Excel1900.normalize(59.0), 60.0);
+        // 16.11.2021
+        assert_eq!(DateSystem::Excel1904.normalize(43054.5), 44516.5);
+        assert_eq!(DateSystem::Excel1904.normalize(0.5), 0.5);
+        assert_eq!(
+            from_excel_date(DateSystem::Excel1904.normalize(24107.0), Offset::UTC),
+            "1970-01-01T00:00:00+00:00"
+        );
+        let dates_1900 = [
+            (1.0, "1900-01-01T00:00:00+03:00"),
+            (59.5, "1900-02-28T12:00:00+03:00"),
+            (61.0, "1900-03-01T00:00:00+03:00"),
+            (25568.0, "1969-12-31T00:00:00+03:00"),
+        ];
+        for (serial, date) in dates_1900 {
+            let normalized = DateSystem::Excel1900.normalize(serial);
+            assert_eq!(from_excel_date(normalized, Offset::MSK), date);
+        }
+        // 02.01.1904 and 31.12.1969
+        assert_eq!(
+            from_excel_date(DateSystem::Excel1904.normalize(1.0), Offset::UTC),
+            "1904-01-02T00:00:00+00:00"
+        );
+        assert_eq!(
+            from_excel_date(DateSystem::Excel1904.normalize(24106.0), Offset::UTC),
+            "1969-12-31T00:00:00+00:00"
+        );
+
+        assert!(xlsx_is_1904(
+            r#"<workbook><workbookPr date1904="1" defaultThemeVersion="124226"/><sheets/></workbook>"#
+        ));
+        assert!(xlsx_is_1904(
+            r#"<x:workbook><x:workbookPr date1904="true"/></x:workbook>"#
+        ));
+        assert!(!xlsx_is_1904(
+            r#"<workbook><workbookPr defaultThemeVersion="124226"/><sheets/></workbook>"#
+        ));
+        assert!(!xlsx_is_1904(r#"<workbook><sheets/></workbook>"#));
+
+        // BrtBeginBook, BrtFileVersion-like record and BrtWbProp
+        let mut buf = vec![0x83, 0x01, 0x00, 0x80, 0x01, 0x02, 0xAA, 0xBB];
+        buf.extend_from_slice(&[0x99, 0x01, 0x0C, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(xlsb_is_1904(&buf));
+        buf[11] = 0x00;
+        assert!(!xlsb_is_1904(&buf));
+        assert!(!xlsb_is_1904(&buf[..4]));
+
+        // xlsb date cells are numbers, they are shifted by the detected system
+        buf[11] = 0x01;
+        let wb_path = env::temp_dir().join("torgi-excel-1904.xlsb");
+        let mut zip = zip::ZipWriter::new(File::create(&wb_path).unwrap());
+        zip.start_file("xl/workbook.bin", zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(&buf).unwrap();
+        zip.finish().unwrap();
+        let dates = date_system(&wb_path, &WorkbookConfig::default());
+        fs::remove_file(&wb_path).unwrap();
+        assert_eq!(dates, DateSystem::Excel1904);
+
+        let cells = [DataType::Float(43054.5)];
+        let row = Row {
+            cells: &cells,
+            row: 4,
+            first_col: 0,
+            dates,
+        };
+        assert_eq!(row.date(Some(Location::column(0))), Some((44516.5, None)));
+    }
+
+    /// Builds compound file of 512 bytes sectors with the only 'Workbook' stream,
+    /// the small one is kept in the mini stream
+    fn compound_file(workbook: &[u8]) -> Vec<u8> {
+        const END: u32 = 0xFFFF_FFFE;
+        let is_mini = workbook.len() < 4096;
+        let mut stream = workbook.to_vec();
+        stream.resize((workbook.len() / 512 + 1) * 512, 0);
+        let stream_sectors = (stream.len() / 512) as u32;
+        let first = if is_mini { 3 } else { 2 };
+
+        let mut header = CFB_SIGNATURE.to_vec();
+        header.resize(0x200, 0);
+        let put = |buf: &mut Vec<u8>, pos: usize, v: u32| {
+            buf[pos..pos + 4].copy_from_slice(&v.to_le_bytes())
+        };
+        header[0x1E] = 9;
+        header[0x20] = 6;
+        put(&mut header, 0x2C, 1);
+        put(&mut header, 0x30, 1);
+        put(&mut header, 0x38, 4096);
+        put(&mut header, 0x3C, if is_mini { 2 } else { END });
+        put(&mut header, 0x44, END);
+        for i in 0..109 {
+            put(&mut header, 0x4C + i * 4, if i == 0 { 0 } else { u32::MAX });
+        }
+
+        let mut fat = vec![0xFFFF_FFFD, END];
+        if is_mini {
+            fat.push(END);
+        }
+        fat.extend((first + 1..first + stream_sectors).chain([END]));
+        fat.resize(128, u32::MAX);
+
+        let mut dir = vec![0; 512];
+        let mut entry = |i: usize, name: &str, typ: u8, start: u32, size: u32| {
+            let e = &mut dir[i * 128..(i + 1) * 128];
+            for (j, c) in name.encode_utf16().enumerate() {
+                e[j * 2..j * 2 + 2].copy_from_slice(&c.to_le_bytes());
+            }
+            e[64] = ((name.len() + 1) * 2) as u8;
+            e[66] = typ;
+            e[116..120].copy_from_slice(&start.to_le_bytes());
+            e[120..124].copy_from_slice(&size.to_le_bytes());
+        };
+        let size = workbook.len() as u32;
+        if is_mini {
+            entry(0, "Root Entry", 5, first, stream.len() as u32);
+            entry(1, "Workbook", 2, 0, size);
+        } else {
+            entry(0, "Root Entry", 5, END, 0);
+            entry(1, "Workbook", 2, first, size);
+        }
+
+        let mut buf = header;
+        buf.extend(fat.iter().flat_map(|id| id.to_le_bytes()));
+        buf.extend(dir);
+        if is_mini {
+            let mini_sectors = (workbook.len() / 64 + 1) as u32;
+            let mut mini_fat: Vec<u32> = (1..mini_sectors).chain([END]).collect();
+            mini_fat.resize(128, u32::MAX);
+            buf.extend(mini_fat.iter().flat_map(|id| id.to_le_bytes()));
+        }
+        buf.extend(stream);
+        buf
+    }
+
+    #[test]
+    fn test_xls_date_system() {
+        // BOF, CODEPAGE, DATEMODE and EOF records of the globals
+        let mut workbook = vec![0x09, 0x08, 0x10, 0x00];
+        workbook.extend_from_slice(&[0; 16]);
+        workbook.extend_from_slice(&[0x42, 0x00, 0x02, 0x00, 0xE3, 0x04]);
+        workbook.extend_from_slice(&[0x22, 0x00, 0x02, 0x00, 0x01, 0x00]);
+        workbook.extend_from_slice(&[0x0A, 0x00, 0x00, 0x00]);
+
+        assert_eq!(xls_is_1904(&compound_file(&workbook)), Some(true));
+        let mut large = workbook.clone();
+        large.resize(5000, 0);
+        assert_eq!(xls_is_1904(&compound_file(&large)), Some(true));
+
+        workbook[30] = 0x00;
+        assert_eq!(xls_is_1904(&compound_file(&workbook)), Some(false));
+        // no DATEMODE record means 1900 system
+        assert_eq!(xls_is_1904(&compound_file(&workbook[..20])), Some(false));
+
+        let mut broken = compound_file(&workbook);
+        broken.truncate(1024);
+        assert_eq!(xls_is_1904(&broken), None);
+        assert_eq!(xls_is_1904(b"not a compound file"), None);
+
+        workbook[30] = 0x01;
+        let wb_path = env::temp_dir().join("torgi-excel-1904.xls");
+        fs::write(&wb_path, compound_file(&workbook)).unwrap();
+        let dates = date_system(&wb_path, &WorkbookConfig::default());
+        fs::remove_file(&wb_path).unwrap();
+        assert_eq!(dates, DateSystem::Excel1904);
+    }
+
+    #[test]
+    fn test_open_wb() {
+        let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
+        assert!(open_wb(Path::new(&wb_path)).is_ok());
+    }
+
+    #[test]
+    fn test_from_excel_date() {
+        assert_eq!(
+            from_excel_date(44516.42361, Offset::UTC),
+            "2021-11-16T10:10:00+00:00".to_string()
+        );
+        assert_eq!(
+            from_excel_date(44621.00, Offset::UTC),
+            "2022-03-01T00:00:00+00:00".to_string()
+        );
+        assert_eq!(
+            from_excel_date(43890.4166666667, Offset::UTC),
+            "2020-02-29T10:00:00+00:00".to_string()
+        );
+        assert_eq!(
+            from_excel_date(44713.00, Offset::UTC),
+            "2022-06-01T00:00:00+00:00".to_string()
+        );
+        // the local time is kept as it is
+        assert_eq!(
+            from_excel_date(44516.42361, Offset::MSK),
+            "2021-11-16T10:10:00+03:00".to_string()
+        );
+        assert_eq!(
+            from_excel_date(44516.42361, "-03:30".parse().unwrap()),
+            "2021-11-16T10:10:00-03:30".to_string()
+        );
     }
 }
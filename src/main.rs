@@ -1,24 +1,222 @@
+mod cli;
+mod config;
 mod daemon;
 mod excel;
+mod money;
+mod participants;
+mod payload;
+mod report;
+mod schema;
 mod simple_time;
-use env_logger;
-use log::{error, info};
-use std::env;
+mod source;
+use cli::Command;
+use config::Config;
+use daemon::DaemonError;
+use excel::Purchase;
+use log::{error, info, warn};
+use payload::{Envelope, Kind};
+use reqwest::blocking::Client;
+use std::{env, io::Write, path::Path, process, thread};
 
-/// Logger initialization
+/// Logger initialization. Messages from the watching threads
+/// are prefixed with the thread name which is the name of watched workbook
 fn enable_logger() {
     env::set_var("RUST_LOG", "info");
-    env_logger::init();
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            let ts = buf.timestamp();
+            let level = buf.default_styled_level(record.level());
+            match thread::current().name() {
+                Some(name) if name != "main" => writeln!(
+                    buf,
+                    "[{} {} {}] [{}] {}",
+                    ts,
+                    level,
+                    record.target(),
+                    name,
+                    record.args()
+                ),
+                _ => writeln!(
+                    buf,
+                    "[{} {} {}] {}",
+                    ts,
+                    level,
+                    record.target(),
+                    record.args()
+                ),
+            }
+        })
+        .init();
+}
+
+/// Prints active state records of the workbook
+/// as json to stdout or to the `output` file
+fn snapshot(config: &Config, output: Option<&str>) -> Result<(), DaemonError> {
+    config.require_workbook()?;
+
+    let (purches, issues) = source::open(&config.workbook).active_state_checked()?;
+    for issue in issues.iter() {
+        warn!("{}", issue);
+    }
+    let workbook = payload::Workbook {
+        name: String::new(),
+        path: config.workbook.path.clone(),
+    };
+    let json = Envelope::new(workbook, 1, Kind::Snapshot, purches.unwrap_or_default())
+        .to_json(config.daemon.payload)
+        .map_err(excel::WorkbookError::from)?;
+
+    match output {
+        Some(o) => daemon::write_to_file(Path::new(o), json)?,
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+/// Prints result of comparing old snapshot
+/// with active state records of the workbook.
+/// Old snapshot may be an envelope or a bare array
+fn diff(config: &Config, old: &str, workbook: &str) -> Result<(), DaemonError> {
+    let old_snapshot = daemon::file_content(Path::new(old))?;
+    let old_snapshot: Envelope<Purchase> =
+        Envelope::from_json(&old_snapshot).map_err(excel::WorkbookError::from)?;
+
+    let mut workbook_config = config.workbook.clone();
+    workbook_config.path = workbook.to_owned();
+    let new_snapshot = source::open(&workbook_config)
+        .active_state()?
+        .unwrap_or_default();
+
+    let changes =
+        excel::active_state_compared(old_snapshot.records, &new_snapshot, &workbook_config);
+    match changes {
+        Some(changes) => {
+            let workbook = payload::Workbook {
+                name: String::new(),
+                path: workbook.to_owned(),
+            };
+            let json = Envelope::new(workbook, old_snapshot.sequence + 1, Kind::Changes, changes)
+                .to_json(config.daemon.payload)
+                .map_err(excel::WorkbookError::from)?;
+            println!("{}", json)
+        }
+        None => info!("no changes in records"),
+    }
+    Ok(())
+}
+
+/// Sends content of the json file to the remote app url
+fn send(config: &Config, file: &str) -> Result<(), DaemonError> {
+    config.require_app_url()?;
+
+    let json = daemon::file_content(Path::new(file))?;
+    daemon::send(&Client::new(), &config.daemon.app_url, json)?;
+    Ok(())
+}
+
+/// Checks the workbook columns and reports the problems
+fn validate(config: &Config) -> Result<bool, DaemonError> {
+    config.require_workbook()?;
+
+    let checks = source::open(&config.workbook).check_columns()?;
+    for check in checks.iter() {
+        // sheet is empty for the sources without sheets
+        let sheet = if check.sheet.is_empty() {
+            String::new()
+        } else {
+            format!("sheet '{}': ", check.sheet)
+        };
+        for field in check.missing.iter() {
+            let names = (
+                config.workbook.schema.names(*field),
+                config.workbook.headers.names(*field),
+            );
+            if field.is_required() {
+                error!(
+                    "{}missing column for required '{}': expected named range {:?} or header {:?}",
+                    sheet, field, names.0, names.1
+                );
+            } else {
+                warn!(
+                    "{}missing column for optional '{}': expected named range {:?} or header {:?}",
+                    sheet, field, names.0, names.1
+                );
+            }
+        }
+        for (field, range) in check.invalid.iter() {
+            error!(
+                "{}invalid named range for '{}': refers to '{}'",
+                sheet, field, range
+            );
+        }
+    }
+
+    if checks.iter().all(|c| c.is_ok()) {
+        info!("all columns are valid");
+    }
+    // missing optional columns are not the reason to fail
+    Ok(!checks.iter().any(|c| c.is_broken()))
+}
+
+/// Prints the cells of active records which values can't be read
+/// as json to stdout or to the `output` file
+fn report(config: &Config, output: Option<&str>) -> Result<bool, DaemonError> {
+    config.require_workbook()?;
+
+    let (_, issues) = source::open(&config.workbook).active_state_checked()?;
+    for issue in issues.iter() {
+        warn!("{}", issue);
+    }
+    let json = report::to_json(&issues).map_err(excel::WorkbookError::from)?;
+
+    match output {
+        Some(o) => daemon::write_to_file(Path::new(o), json)?,
+        None => println!("{}", json),
+    }
+    Ok(issues.is_empty())
+}
+
+fn run(config: &Config, command: &Command) -> Result<bool, DaemonError> {
+    match command {
+        Command::Watch => daemon::watch(config.targets()?)?,
+        Command::Snapshot { output } => snapshot(config, output.as_deref())?,
+        Command::Diff { old, workbook } => diff(config, old, workbook)?,
+        Command::Send { file } => send(config, file)?,
+        Command::Validate => return validate(config),
+        Command::Report { output } => return report(config, output.as_deref()),
+    }
+    Ok(true)
 }
 
 fn main() {
     enable_logger();
 
-    let wb_path = env::var("REG_WORKBOOK_PATH").expect("$REG_WORKBOOK_PATH must be set");
-    let app_url = env::var("TGBOT_APP_URL").expect("$TGBOT_APP_URL must be set");
+    let cli = match cli::parse(env::args().skip(1)) {
+        Ok(c) => c,
+        Err(cli::CliError::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}", &e);
+            process::exit(2);
+        }
+    };
+
+    let config = match Config::load(cli.config.as_deref()) {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{}", &e);
+            process::exit(1);
+        }
+    };
 
-    match daemon::watch(&wb_path, &app_url) {
-        Ok(()) => info!("done"),
-        Err(e) => error!("{:?}", &e),
+    match run(&config, &cli.command) {
+        Ok(true) => info!("done"),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            error!("{}", &e);
+            process::exit(1);
+        }
     };
 }
@@ -0,0 +1,198 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{convert::TryFrom, fmt};
+
+const KOPECKS_IN_RUBLE: i64 = 100;
+// currency that may follow the amount
+const CURRENCIES: [&str; 5] = ["рублей", "руб.", "руб", "р.", "₽"];
+
+/// Amount of money in rubles and kopecks. It's kept as the number
+/// of kopecks, so there is no rounding noise when amounts are compared.
+/// In json it's a number of rubles e.g. 1234567.89
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Money(i64);
+
+impl Money {
+    pub const fn from_kopecks(kopecks: i64) -> Self {
+        Money(kopecks)
+    }
+
+    /// Converts the number of rubles e.g. excel cell value
+    /// to money rounding it to kopecks
+    pub fn from_rubles(rubles: f64) -> Option<Self> {
+        let kopecks = (rubles * KOPECKS_IN_RUBLE as f64).round();
+        if !kopecks.is_finite() || kopecks.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        Some(Money::from_kopecks(kopecks as i64))
+    }
+
+    /// Parses amount that is typed as text e.g. '1 234 567,89 руб.'.
+    /// Spaces are thousands separators, comma or dot is decimal one.
+    /// If there are both, the last one is decimal e.g. '1.234.567,89'.
+    /// The only separator before three digits e.g. '1.234' may be either,
+    /// so it's not parsed unless thousands are separated by spaces.
+    /// Fractions of kopecks are rounded
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut s = s.trim().to_lowercase();
+        for currency in CURRENCIES.iter() {
+            if let Some(stripped) = s.strip_suffix(currency) {
+                s = stripped.to_owned();
+                break;
+            }
+        }
+        let spaced = s.trim().contains(char::is_whitespace);
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+
+        let separators = s.matches([',', '.']).count();
+        let (int, frac) = match s.rfind([',', '.']) {
+            // '1,234,567' has no fraction
+            Some(i) if separators > 1 && s.matches(&s[i..=i]).count() == separators => (&s[..], ""),
+            // '1.234' is 1234 or 1.23, but '0,005' and '1 234,567' are fractions
+            Some(i) if separators == 1 && s.len() - i == 4 && &s[..i] != "0" && !spaced => {
+                return None
+            }
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (&s[..], ""),
+        };
+        let int: String = int.chars().filter(|c| *c != ',' && *c != '.').collect();
+
+        if int.is_empty() || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let rubles: i64 = int.parse().ok()?;
+        let mut digits = frac.chars().map(|c| c as i64 - '0' as i64);
+        let mut kopecks = digits.next().unwrap_or(0) * 10 + digits.next().unwrap_or(0);
+        if digits.next().unwrap_or(0) >= 5 {
+            kopecks += 1;
+        }
+
+        rubles
+            .checked_mul(KOPECKS_IN_RUBLE)?
+            .checked_add(kopecks)
+            .map(Money::from_kopecks)
+    }
+
+    /// Number of rubles with kopecks as a fraction
+    pub fn to_rubles(self) -> f64 {
+        self.0 as f64 / KOPECKS_IN_RUBLE as f64
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let (rubles, kopecks) = (abs / 100, abs % 100);
+        write!(f, "{}{}.{:02}", sign, rubles, kopecks)
+    }
+}
+
+// the shortest float that is parsed back to the same amount
+impl Serialize for Money {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_f64(self.to_rubles())
+    }
+}
+
+// number of rubles or text amount
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct MoneyVisitor;
+
+        impl<'de> de::Visitor<'de> for MoneyVisitor {
+            type Value = Money;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "amount of rubles")
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+                Money::from_rubles(v).ok_or_else(|| E::custom(format!("invalid amount {}", v)))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+                v.checked_mul(KOPECKS_IN_RUBLE)
+                    .map(Money::from_kopecks)
+                    .ok_or_else(|| E::custom(format!("invalid amount {}", v)))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+                i64::try_from(v)
+                    .map_err(|_| E::custom(format!("invalid amount {}", v)))
+                    .and_then(|v| self.visit_i64(v))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+                Money::parse(v).ok_or_else(|| E::custom(format!("invalid amount '{}'", v)))
+            }
+        }
+
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let m = Money::from_kopecks;
+        assert_eq!(Money::parse("1 234 567,89 руб."), Some(m(123456789)));
+        assert_eq!(Money::parse("1\u{a0}234\u{a0}567.89"), Some(m(123456789)));
+        assert_eq!(Money::parse("1.234.567,89"), Some(m(123456789)));
+        assert_eq!(Money::parse("1,234,567"), Some(m(123456700)));
+        assert_eq!(Money::parse("1 250 000 ₽"), Some(m(125000000)));
+        assert_eq!(Money::parse("99,9 рублей"), Some(m(9990)));
+        assert_eq!(Money::parse("0,005"), Some(m(1)));
+        assert_eq!(Money::parse("1 234,567"), Some(m(123457)));
+        assert_eq!(Money::parse("1.234"), None);
+        assert_eq!(Money::parse("12,345 руб."), None);
+        assert_eq!(Money::parse("1.2345"), Some(m(123)));
+        assert_eq!(Money::parse("12З4"), None);
+        assert_eq!(Money::parse("руб."), None);
+        assert_eq!(Money::parse(",5"), None);
+        assert_eq!(Money::parse("-5"), None);
+        assert_eq!(Money::parse("99999999999999999999"), None);
+    }
+
+    #[test]
+    fn test_from_rubles() {
+        assert_eq!(
+            Money::from_rubles(1234567.89),
+            Some(Money::from_kopecks(123456789))
+        );
+        assert_eq!(Money::from_rubles(0.1 + 0.2), Some(Money::from_kopecks(30)));
+        assert_eq!(Money::from_rubles(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Money::from_kopecks(123456789).to_string(), "1234567.89");
+        assert_eq!(Money::from_kopecks(5).to_string(), "0.05");
+        assert_eq!(Money::from_kopecks(-150).to_string(), "-1.50");
+    }
+
+    #[test]
+    fn test_json() {
+        let m = Money::from_kopecks(123456789);
+        assert_eq!(serde_json::to_string(&m).unwrap(), "1234567.89");
+        assert_eq!(serde_json::from_str::<Money>("1234567.89").unwrap(), m);
+        assert_eq!(
+            serde_json::from_str::<Money>("\"1 234 567,89\"").unwrap(),
+            m
+        );
+        assert_eq!(
+            serde_json::from_str::<Money>("1250000").unwrap(),
+            Money::from_kopecks(125000000)
+        );
+        assert!(serde_json::from_str::<Money>("\"много\"").is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::money::Money;
+use serde::{Deserialize, Serialize};
+
+const INN: &str = "инн";
+
+/// Participant of the purchase as it is typed in the workbook
+/// e.g. 'ООО Ромашка, ИНН 7701234567 (1 250 000)'
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Participant {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inn: Option<String>,
+    // offered price if it's known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<Money>,
+}
+
+/// Parses the list of participants separated by ';' or new lines.
+/// Price is expected in parentheses or after a dash, INN is marked as 'ИНН'
+pub fn parse(text: &str) -> Vec<Participant> {
+    text.split([';', '\n'])
+        .filter_map(parse_participant)
+        .collect()
+}
+
+/// Parses one participant. Parentheses that are neither price
+/// nor INN e.g. '(филиал)' are the part of the name
+fn parse_participant(item: &str) -> Option<Participant> {
+    let mut participant = Participant::default();
+    let mut name = String::new();
+
+    let mut rest = item;
+    while let Some(open) = rest.find('(') {
+        let close = match rest[open..].find(')') {
+            Some(c) => open + c,
+            None => break,
+        };
+        let inner = &rest[open + 1..close];
+        name.push_str(&rest[..open]);
+
+        if let Some((range, inn)) = find_inn(inner) {
+            participant.inn = participant.inn.or(Some(inn));
+            // the rest is kept e.g. '(ИНН 7701234567, филиал)'
+            let other = format!("{}{}", &inner[..range.start], &inner[range.end..]);
+            let other = trim_separators(&other);
+            if let Some(price) = Money::parse(other) {
+                participant.price = participant.price.or(Some(price));
+            } else if !other.is_empty() {
+                name.push_str(&format!(" ({})", other));
+            }
+        } else if let Some(price) = Money::parse(inner) {
+            participant.price = participant.price.or(Some(price));
+        } else {
+            name.push_str(&rest[open..=close]);
+        }
+        rest = &rest[close + 1..];
+    }
+    name.push_str(rest);
+
+    if let Some((range, inn)) = find_inn(&name) {
+        participant.inn = participant.inn.or(Some(inn));
+        name.replace_range(range, " ");
+    }
+
+    // 'ООО Ромашка — 1 250 000', but not 'ООО Ромашка-2000'
+    if participant.price.is_none() {
+        if let Some(dash) = name.rfind(is_dash) {
+            let tail = name[dash..].trim_start_matches(is_dash);
+            if name[..dash].ends_with(char::is_whitespace) {
+                if let Some(price) = Money::parse(tail) {
+                    participant.price = Some(price);
+                    name.truncate(dash);
+                }
+            }
+        }
+    }
+
+    participant.name = trim_separators(&name)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if participant.name.is_empty() && participant.inn.is_none() {
+        return None;
+    }
+    Some(participant)
+}
+
+fn is_dash(c: char) -> bool {
+    c == '-' || c == '–' || c == '—'
+}
+
+fn trim_separators(s: &str) -> &str {
+    s.trim_matches(|c: char| c.is_whitespace() || c == ',' || c == ':' || is_dash(c))
+}
+
+/// Finds 'ИНН 7701234567' and returns it's byte range and the digits.
+/// INN is 10 digits for organizations and 12 for individuals
+fn find_inn(s: &str) -> Option<(std::ops::Range<usize>, String)> {
+    // the case is compared by chars, so the positions are of the original string.
+    // 'инн' that isn't followed by the digits e.g. of 'Иннотех' is skipped
+    s.char_indices().find_map(|(start, _)| {
+        let mut chars = s[start..].char_indices();
+        let word: String = chars
+            .by_ref()
+            .take(INN.chars().count())
+            .flat_map(|(_, c)| c.to_lowercase())
+            .collect();
+        if word != INN {
+            return None;
+        }
+
+        let word_end = chars.next().map_or(s.len(), |(i, _)| start + i);
+        let tail =
+            s[word_end..].trim_start_matches(|c: char| c == ':' || c == '№' || c.is_whitespace());
+        let digits_start = s.len() - tail.len();
+        let digits: String = s[digits_start..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+
+        if digits.len() != 10 && digits.len() != 12 {
+            return None;
+        }
+        Some((start..digits_start + digits.len(), digits))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn participant(name: &str, inn: Option<&str>, price: Option<i64>) -> Participant {
+        Participant {
+            name: name.to_owned(),
+            inn: inn.map(String::from),
+            price: price.map(Money::from_kopecks),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("ООО Ромашка (1 250 000); ИП Иванов (1 300 000)"),
+            [
+                participant("ООО Ромашка", None, Some(125000000)),
+                participant("ИП Иванов", None, Some(130000000)),
+            ]
+        );
+        assert_eq!(
+            parse("ООО \"Ромашка\", ИНН 7701234567 (1 250 000,50 руб.)\nИП Иванов (ИНН 770123456789) — 1 300 000 ₽;"),
+            [
+                participant("ООО \"Ромашка\"", Some("7701234567"), Some(125000050)),
+                participant("ИП Иванов", Some("770123456789"), Some(130000000)),
+            ]
+        );
+        assert_eq!(
+            parse("АО Василек (филиал); ООО Ромашка-Юг"),
+            [
+                participant("АО Василек (филиал)", None, None),
+                participant("ООО Ромашка-Юг", None, None),
+            ]
+        );
+        assert_eq!(
+            parse("ООО Ромашка-2000 - 5 000"),
+            [participant("ООО Ромашка-2000", None, Some(500000))]
+        );
+        assert_eq!(
+            parse("АО Василек (ИНН 7701234567, филиал); ООО Ромашка (инн 7701234568 – 5 000)"),
+            [
+                participant("АО Василек (филиал)", Some("7701234567"), None),
+                participant("ООО Ромашка", Some("7701234568"), Some(500000)),
+            ]
+        );
+        assert!(parse("").is_empty());
+        assert!(parse(" ; ").is_empty());
+    }
+
+    #[test]
+    fn test_find_inn() {
+        assert_eq!(
+            find_inn("ООО Ромашка ИНН: 7701234567"),
+            Some((22..40, "7701234567".to_owned()))
+        );
+        // lowercase of 'İ' is longer than the letter itself
+        assert_eq!(
+            find_inn("İ ООО ИНН 7701234567"),
+            Some((10..27, "7701234567".to_owned()))
+        );
+        assert_eq!(
+            find_inn("ООО Иннотех, Инн 7701234567"),
+            Some((23..40, "7701234567".to_owned()))
+        );
+        assert_eq!(find_inn("инн 123"), None);
+        assert_eq!(find_inn("ООО Ромашка"), None);
+    }
+}
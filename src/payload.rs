@@ -0,0 +1,143 @@
+use crate::simple_time::Moment;
+use serde::{de, de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+/// Version of the envelope format. It's increased
+/// when the bot can't read the payload as it did before
+pub const VERSION: u32 = 1;
+
+/// How the records are sent to the app
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Payload {
+    // records with the metadata
+    #[default]
+    Envelope,
+    // bare array of records as it was before the envelope
+    Array,
+}
+
+/// What the records of the payload are
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    // every active record
+    Snapshot,
+    // records that are new, changed or felt off since the previous payload
+    Changes,
+}
+
+/// Identity of the workbook that the records are read from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Workbook {
+    // name of the watched workbook, empty for the commands
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub path: String,
+}
+
+/// Records with the metadata. The sequence number grows by one with every
+/// sent payload of the workbook, so the bot can notice the missed ones
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Envelope<T> {
+    pub version: u32,
+    pub generated_at: String, // RFC3339 in UTC
+    pub workbook: Workbook,
+    pub sequence: u64,
+    pub kind: Kind,
+    pub records: Vec<T>,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(workbook: Workbook, sequence: u64, kind: Kind, records: Vec<T>) -> Self {
+        Self {
+            version: VERSION,
+            generated_at: Moment::now().map(|m| m.to_string()).unwrap_or_default(),
+            workbook,
+            sequence,
+            kind,
+            records,
+        }
+    }
+}
+
+impl<T: Serialize> Envelope<T> {
+    /// Converts the payload to json string in the given format
+    pub fn to_json(&self, payload: Payload) -> serde_json::Result<String> {
+        match payload {
+            Payload::Envelope => serde_json::to_string(self),
+            Payload::Array => serde_json::to_string(&self.records),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Envelope<T> {
+    /// Parses the envelope or the bare array of records
+    /// e.g. old snapshot, which has no metadata and sequence 0
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let value: Value = serde_json::from_str(json)?;
+        if value.is_array() {
+            return Ok(Self {
+                version: 0,
+                generated_at: String::new(),
+                workbook: Workbook::default(),
+                sequence: 0,
+                kind: Kind::Snapshot,
+                records: serde_json::from_value(value)?,
+            });
+        }
+
+        let envelope: Self = serde_json::from_value(value)?;
+        if envelope.version > VERSION {
+            return Err(de::Error::custom(format!(
+                "payload version {} is newer than {}",
+                envelope.version, VERSION
+            )));
+        }
+        Ok(envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workbook() -> Workbook {
+        Workbook {
+            name: "main".to_owned(),
+            path: "reg.xlsx".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let envelope = Envelope {
+            generated_at: "2021-11-16T07:00:00+00:00".to_owned(),
+            ..Envelope::new(workbook(), 5, Kind::Changes, vec![1, 2])
+        };
+        assert_eq!(
+            envelope.to_json(Payload::Envelope).unwrap(),
+            r#"{"version":1,"generated_at":"2021-11-16T07:00:00+00:00","workbook":{"name":"main","path":"reg.xlsx"},"sequence":5,"kind":"changes","records":[1,2]}"#
+        );
+        assert_eq!(envelope.to_json(Payload::Array).unwrap(), "[1,2]");
+    }
+
+    #[test]
+    fn test_from_json() {
+        let json = Envelope::new(workbook(), 5, Kind::Snapshot, vec![1, 2])
+            .to_json(Payload::Envelope)
+            .unwrap();
+        let envelope: Envelope<i32> = Envelope::from_json(&json).unwrap();
+        assert_eq!(envelope.workbook, workbook());
+        assert_eq!(envelope.sequence, 5);
+        assert_eq!(envelope.records, [1, 2]);
+
+        let envelope: Envelope<i32> = Envelope::from_json("[1, 2]").unwrap();
+        assert_eq!((envelope.version, envelope.sequence), (0, 0));
+        assert_eq!(envelope.records, [1, 2]);
+
+        let json = json.replace("\"version\":1", "\"version\":2");
+        assert!(Envelope::<i32>::from_json(&json).is_err());
+        assert!(Envelope::<i32>::from_json("{\"records\": []}").is_err());
+    }
+}
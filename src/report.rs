@@ -0,0 +1,80 @@
+use crate::schema::Field;
+use serde::Serialize;
+use std::fmt;
+
+/// Problem of the cell value that is lost while reading records
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Problem {
+    MissingValue,
+    NotANumber,
+    InvalidDate,
+    CellError,     // e.g. '#REF!' or '#N/A'
+    RoundedNumber, // long number that excel has already rounded
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::MissingValue => write!(f, "missing value"),
+            Problem::NotANumber => write!(f, "not a number"),
+            Problem::InvalidDate => write!(f, "invalid date"),
+            Problem::CellError => write!(f, "cell error"),
+            Problem::RoundedNumber => write!(f, "rounded number"),
+        }
+    }
+}
+
+/// Cell of the source which value can't be read as is
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CellIssue {
+    pub sheet: String,
+    pub row: u32,       // starts from 1 as in excel
+    pub column: String, // column letter e.g. 'AB'
+    pub field: Field,
+    pub problem: Problem,
+    pub value: String, // raw value of the cell
+}
+
+impl fmt::Display for CellIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.sheet.is_empty() {
+            write!(f, "sheet '{}' ", self.sheet)?;
+        }
+        write!(
+            f,
+            "cell {}{} ({}): {} '{}'",
+            self.column, self.row, self.field, self.problem, self.value
+        )
+    }
+}
+
+/// Converts the report to json string
+pub fn to_json(issues: &[CellIssue]) -> serde_json::Result<String> {
+    serde_json::to_string(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_issue() {
+        let issue = CellIssue {
+            sheet: "Реестр".to_owned(),
+            row: 5,
+            column: "L".to_owned(),
+            field: Field::MaxPrice,
+            problem: Problem::NotANumber,
+            value: "1 2З4,5".to_owned(),
+        };
+        assert_eq!(
+            issue.to_string(),
+            "sheet 'Реестр' cell L5 (max_price): not a number '1 2З4,5'"
+        );
+        assert_eq!(
+            to_json(&[issue]).unwrap(),
+            r#"[{"sheet":"Реестр","row":5,"column":"L","field":"max_price","problem":"not_a_number","value":"1 2З4,5"}]"#
+        );
+    }
+}
@@ -0,0 +1,582 @@
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+const PURCHASE_SUBJECT_ABBR: &str = "Предмет";
+const PURCHASE_SUBJECT: &str = "Поставляемые_товары";
+const DATE_COLLECTING_BIDS: &str = "Дата_окончания_подачи_заявок";
+const TIME_COLLECTING_BIDS: &str = "Время_окончания_подачи_заявок";
+const DATE_APPROVAL: &str = "Дата_окончания_срока_рассмотрения_заявок";
+const DATE_BIDDING: &str = "Дата_проведения_аукциона_конкурса";
+const TIME_BIDDING: &str = "Время_проведения_аукциона_конкурса";
+const REGION: &str = "Регион";
+const CUSTOMER_TYPE: &str = "Заказчик";
+const MAX_PRICE: &str = "НМЦК";
+const APPLICATION_GUARANTEE: &str = "Размер_обеспечения_заявки";
+const CONTRACT_GUARANTEE: &str = "Размер_обеспечения_контракта";
+const STATUS: &str = "Статус";
+const OUR_PARTICIPANTS: &str = "Наши_участники";
+const ESTIMATION: &str = "Расчет";
+const NUMBER: &str = "Номер";
+const LOT_NUMBER: &str = "Номер_лота";
+const PURCHASE_TYPE: &str = "Форма_проведения";
+const ETP: &str = "Площадка";
+const WINNER: &str = "Победитель";
+const WINNER_PRICE: &str = "Сумма_выигранного_лота";
+const PARTICIPANTS: &str = "Участники";
+const STATUS_GO: &str = "идем";
+const STATUS_NOT_GO: &str = "не идем";
+const STATUS_ADMITTED: &str = "допущены";
+const STATUS_APPLY: &str = "заявлены";
+const STATUS_WIN: &str = "выиграли";
+const STATUS_LOSS: &str = "не выиграли";
+const STATUS_ESTIMATION: &str = "расчет";
+
+/// Workbook column that is mapped to the [crate::excel::Purchase] field
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    RegistryNumber,
+    LotNumber,
+    PurchaseSubject,
+    PurchaseAbbr,
+    PurchaseType,
+    CollectingDate,
+    CollectingDatetime,
+    ApprovalDatetime,
+    BiddingDate,
+    BiddingDatetime,
+    Region,
+    CustomerType,
+    MaxPrice,
+    ApplicationGuarantee,
+    ContractGuarantee,
+    Status,
+    OurParticipants,
+    Estimation,
+    Etp,
+    Winner,
+    WinnerPrice,
+    Participants,
+}
+
+impl Field {
+    pub const ALL: [Field; 22] = [
+        Field::RegistryNumber,
+        Field::LotNumber,
+        Field::PurchaseSubject,
+        Field::PurchaseAbbr,
+        Field::PurchaseType,
+        Field::CollectingDate,
+        Field::CollectingDatetime,
+        Field::ApprovalDatetime,
+        Field::BiddingDate,
+        Field::BiddingDatetime,
+        Field::Region,
+        Field::CustomerType,
+        Field::MaxPrice,
+        Field::ApplicationGuarantee,
+        Field::ContractGuarantee,
+        Field::Status,
+        Field::OurParticipants,
+        Field::Estimation,
+        Field::Etp,
+        Field::Winner,
+        Field::WinnerPrice,
+        Field::Participants,
+    ];
+
+    /// Name of the field as it is written in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Field::RegistryNumber => "registry_number",
+            Field::LotNumber => "lot_number",
+            Field::PurchaseSubject => "purchase_subject",
+            Field::PurchaseAbbr => "purchase_abbr",
+            Field::PurchaseType => "purchase_type",
+            Field::CollectingDate => "collecting_date",
+            Field::CollectingDatetime => "collecting_datetime",
+            Field::ApprovalDatetime => "approval_datetime",
+            Field::BiddingDate => "bidding_date",
+            Field::BiddingDatetime => "bidding_datetime",
+            Field::Region => "region",
+            Field::CustomerType => "customer_type",
+            Field::MaxPrice => "max_price",
+            Field::ApplicationGuarantee => "application_guarantee",
+            Field::ContractGuarantee => "contract_guarantee",
+            Field::Status => "status",
+            Field::OurParticipants => "our_participants",
+            Field::Estimation => "estimation",
+            Field::Etp => "etp",
+            Field::Winner => "winner",
+            Field::WinnerPrice => "winner_price",
+            Field::Participants => "participants",
+        }
+    }
+
+    /// Returns the field by it's config name
+    pub fn from_name(s: &str) -> Option<Field> {
+        Field::ALL.iter().copied().find(|f| f.as_str() == s)
+    }
+
+    /// Required fields must be located in workbook, otherwise
+    /// records can't be built. The others are left empty if missing
+    pub fn is_required(&self) -> bool {
+        matches!(
+            self,
+            Field::RegistryNumber | Field::Status | Field::BiddingDate
+        )
+    }
+
+    /// Fields that every workbook is expected to have, so the missing ones
+    /// are reported. The others e.g. lot number are read only if present
+    pub fn is_expected(&self) -> bool {
+        !matches!(self, Field::LotNumber)
+    }
+
+    /// Workbook defined name that is expected by default
+    fn default_name(&self) -> &'static str {
+        match self {
+            Field::RegistryNumber => NUMBER,
+            Field::LotNumber => LOT_NUMBER,
+            Field::PurchaseSubject => PURCHASE_SUBJECT,
+            Field::PurchaseAbbr => PURCHASE_SUBJECT_ABBR,
+            Field::PurchaseType => PURCHASE_TYPE,
+            Field::CollectingDate => DATE_COLLECTING_BIDS,
+            Field::CollectingDatetime => TIME_COLLECTING_BIDS,
+            Field::ApprovalDatetime => DATE_APPROVAL,
+            Field::BiddingDate => DATE_BIDDING,
+            Field::BiddingDatetime => TIME_BIDDING,
+            Field::Region => REGION,
+            Field::CustomerType => CUSTOMER_TYPE,
+            Field::MaxPrice => MAX_PRICE,
+            Field::ApplicationGuarantee => APPLICATION_GUARANTEE,
+            Field::ContractGuarantee => CONTRACT_GUARANTEE,
+            Field::Status => STATUS,
+            Field::OurParticipants => OUR_PARTICIPANTS,
+            Field::Estimation => ESTIMATION,
+            Field::Etp => ETP,
+            Field::Winner => WINNER,
+            Field::WinnerPrice => WINNER_PRICE,
+            Field::Participants => PARTICIPANTS,
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Mapping of workbook defined names (or column headers) to the [Field]'s.
+/// Every field may have several names i.e. alternative spellings.
+/// Names are compared case insensitively as excel does,
+/// underscores are equal to spaces so `Дата_торгов` matches `Дата торгов`
+#[derive(Debug, Clone)]
+pub struct Schema {
+    names: HashMap<Field, Vec<String>>,
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Self {
+            names: Field::ALL
+                .iter()
+                .map(|f| (*f, vec![f.default_name().to_owned()]))
+                .collect(),
+        }
+    }
+}
+
+// fields that are set in config replace the default names,
+// the others keep them
+impl<'de> Deserialize<'de> for Schema {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // toml doesn't support enum as a key of table
+        let names: HashMap<String, Vec<String>> = HashMap::deserialize(deserializer)?;
+
+        let mut schema = Schema::default();
+        let mut seen: HashMap<String, Field> = HashMap::new();
+
+        for (key, names) in names {
+            let field = Field::from_name(&key)
+                .ok_or_else(|| de::Error::custom(format!("unknown field '{}'", key)))?;
+            if names.is_empty() {
+                return Err(de::Error::custom(format!(
+                    "field '{}' must have at least one name",
+                    field
+                )));
+            }
+            schema.names.insert(field, names);
+        }
+
+        for field in Field::ALL.iter() {
+            for name in schema.names(*field) {
+                if let Some(other) = seen.insert(normalize(name), *field) {
+                    return Err(de::Error::custom(format!(
+                        "name '{}' is used for both '{}' and '{}'",
+                        name, other, field
+                    )));
+                }
+            }
+        }
+
+        Ok(schema)
+    }
+}
+
+impl Schema {
+    /// Returns the field that is mapped to workbook defined name
+    /// or to the column header if any
+    pub fn field(&self, name: &str) -> Option<Field> {
+        let name = normalize(name);
+        Field::ALL
+            .iter()
+            .copied()
+            .find(|f| self.names(*f).iter().any(|n| normalize(n) == name))
+    }
+
+    /// Returns all the names of the field
+    pub fn names(&self, field: Field) -> &[String] {
+        self.names.get(&field).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+}
+
+/// Normalizes defined name or header for comparison
+fn normalize(s: &str) -> String {
+    s.replace('_', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .replace('ё', "е")
+}
+
+/// Column that is not known to [Field]'s e.g. responsible manager.
+/// It's located in the same way by defined names or headers
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Extra {
+    pub names: Vec<String>,
+    // changes of the value make the record changed
+    #[serde(default)]
+    pub compare: bool,
+}
+
+/// Extra columns that are passed to the `extra` map of the records
+/// as they are. The key is the name of the value in the map
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct Extras {
+    columns: BTreeMap<String, Extra>,
+}
+
+impl Extras {
+    /// Returns the key of the column that is mapped
+    /// to workbook defined name or to the column header if any
+    pub fn key(&self, name: &str) -> Option<&str> {
+        let name = normalize(name);
+        self.columns
+            .iter()
+            .find(|(_, e)| e.names.iter().any(|n| normalize(n) == name))
+            .map(|(k, _)| k.as_str())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.columns.keys().map(|k| k.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Returns the keys of the columns which changes make the record changed
+    pub fn compared(&self) -> impl Iterator<Item = &str> {
+        self.columns
+            .iter()
+            .filter(|(_, e)| e.compare)
+            .map(|(k, _)| k.as_str())
+    }
+
+    /// Checks that every column has a name that isn't used
+    /// by another column or by any field of the schemas
+    pub fn validate(&self, schemas: &[&Schema]) -> Result<(), String> {
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        for (key, extra) in self.columns.iter() {
+            if extra.names.is_empty() {
+                return Err(format!("column '{}' must have at least one name", key));
+            }
+            for name in extra.names.iter() {
+                if let Some(field) = schemas.iter().find_map(|s| s.field(name)) {
+                    return Err(format!(
+                        "name '{}' is used for both '{}' and '{}'",
+                        name, field, key
+                    ));
+                }
+                if let Some(other) = seen.insert(normalize(name), key) {
+                    return Err(format!(
+                        "name '{}' is used for both '{}' and '{}'",
+                        name, other, key
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How the columns of the workbook sheet are located
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Columns {
+    // by workbook defined names
+    #[default]
+    NamedRanges,
+    // by text in the header row
+    Headers,
+    // by defined names, missing ones are filled from the header row
+    Hybrid,
+}
+
+/// What to do with the records that have the same registry number
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Duplicates {
+    #[default]
+    FirstWins,
+    LastWins,
+    // refuse to build the snapshot until duplicates are fixed
+    Reject,
+}
+
+/// Vocabulary of the purchase statuses. Records with an active status
+/// are sent to the app, records that felt off from the active state
+/// are sent with the `removed` status
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Statuses {
+    pub active: Vec<String>,
+    pub removed: String,
+    // compare statuses case insensitively, 'ё' is equal to 'е'
+    pub ignore_case: bool,
+    // ignore leading, trailing and repeated whitespaces
+    pub ignore_whitespace: bool,
+}
+
+impl Default for Statuses {
+    fn default() -> Self {
+        Self {
+            active: [
+                STATUS_GO,
+                STATUS_ADMITTED,
+                STATUS_APPLY,
+                STATUS_WIN,
+                STATUS_LOSS,
+                STATUS_ESTIMATION,
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+            removed: STATUS_NOT_GO.to_owned(),
+            ignore_case: true,
+            ignore_whitespace: true,
+        }
+    }
+}
+
+impl Statuses {
+    /// Returns the active status as it is written in config
+    /// if `s` matches one of them
+    pub fn active(&self, s: &str) -> Option<&str> {
+        let s = self.normalize(s);
+        self.active
+            .iter()
+            .find(|a| self.normalize(a) == s)
+            .map(|a| a.as_str())
+    }
+
+    /// Checks that statuses make sense
+    pub fn validate(&self) -> Result<(), String> {
+        if self.active.is_empty() {
+            return Err("at least one active status must be set".to_owned());
+        }
+        if self.removed.trim().is_empty() {
+            return Err("removed status must be set".to_owned());
+        }
+        if self.active(&self.removed).is_some() {
+            return Err(format!("removed status '{}' is active", self.removed));
+        }
+        Ok(())
+    }
+
+    fn normalize(&self, s: &str) -> String {
+        let s = if self.ignore_whitespace {
+            s.split_whitespace().collect::<Vec<_>>().join(" ")
+        } else {
+            s.to_owned()
+        };
+        if self.ignore_case {
+            s.to_lowercase().replace('ё', "е")
+        } else {
+            s
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_field() {
+        let schema = Schema::default();
+        assert_eq!(
+            schema.field(DATE_COLLECTING_BIDS),
+            Some(Field::CollectingDate)
+        );
+        assert_eq!(
+            schema.field(TIME_COLLECTING_BIDS),
+            Some(Field::CollectingDatetime)
+        );
+        assert_eq!(schema.field(DATE_APPROVAL), Some(Field::ApprovalDatetime));
+        assert_eq!(schema.field(DATE_BIDDING), Some(Field::BiddingDate));
+        assert_eq!(schema.field(TIME_BIDDING), Some(Field::BiddingDatetime));
+        assert_eq!(schema.field(MAX_PRICE), Some(Field::MaxPrice));
+        assert_eq!(schema.field(NUMBER), Some(Field::RegistryNumber));
+        assert_eq!(schema.field(WINNER), Some(Field::Winner));
+        assert_eq!(schema.field(STATUS), Some(Field::Status));
+        assert_eq!(schema.field(WINNER_PRICE), Some(Field::WinnerPrice));
+        assert_eq!(schema.field(PARTICIPANTS), Some(Field::Participants));
+        assert_eq!(schema.field(PURCHASE_TYPE), Some(Field::PurchaseType));
+        assert_eq!(schema.field(PURCHASE_SUBJECT), Some(Field::PurchaseSubject));
+        assert_eq!(
+            schema.field(PURCHASE_SUBJECT_ABBR),
+            Some(Field::PurchaseAbbr)
+        );
+        assert_eq!(schema.field(OUR_PARTICIPANTS), Some(Field::OurParticipants));
+        assert_eq!(schema.field(ETP), Some(Field::Etp));
+        assert_eq!(
+            schema.field(APPLICATION_GUARANTEE),
+            Some(Field::ApplicationGuarantee)
+        );
+        assert_eq!(
+            schema.field(CONTRACT_GUARANTEE),
+            Some(Field::ContractGuarantee)
+        );
+        assert_eq!(schema.field(CUSTOMER_TYPE), Some(Field::CustomerType));
+        assert_eq!(schema.field(ESTIMATION), Some(Field::Estimation));
+        assert_eq!(schema.field(REGION), Some(Field::Region));
+        assert_eq!(schema.field("invalid name"), None);
+    }
+
+    #[test]
+    fn test_extras() {
+        let extras: Extras = toml::from_str(
+            r#"
+            manager = { names = ["Ответственный", "Менеджер"], compare = true }
+            notes = { names = ["Примечание"] }
+        "#,
+        )
+        .unwrap();
+        assert_eq!(extras.key("ответственный"), Some("manager"));
+        assert_eq!(extras.key("Примечание"), Some("notes"));
+        assert_eq!(extras.key(STATUS), None);
+        assert!(extras.validate(&[&Schema::default()]).is_ok());
+
+        assert_eq!(extras.compared().collect::<Vec<_>>(), ["manager"]);
+
+        let extras: Extras = toml::from_str(r#"status = { names = ["Статус"] }"#).unwrap();
+        assert_eq!(
+            extras.validate(&[&Schema::default()]),
+            Err("name 'Статус' is used for both 'status' and 'status'".to_owned())
+        );
+        let extras: Extras = toml::from_str(
+            r#"
+            a = { names = ["Примечание"] }
+            b = { names = ["примечание"] }
+        "#,
+        )
+        .unwrap();
+        assert!(extras.validate(&[]).is_err());
+        assert!(toml::from_str::<Extras>(r#"a = { names = [], comapre = true }"#).is_err());
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let schema: Schema = toml::from_str(
+            r#"
+            registry_number = ["Номер", "Номер_извещения"]
+            status = ["Статус_закупки"]
+        "#,
+        )
+        .unwrap();
+        assert_eq!(schema.field("Номер"), Some(Field::RegistryNumber));
+        assert_eq!(schema.field("номер_извещения"), Some(Field::RegistryNumber));
+        assert_eq!(schema.field("Статус_закупки"), Some(Field::Status));
+        assert_eq!(schema.field(STATUS), None);
+        assert_eq!(schema.field(REGION), Some(Field::Region));
+    }
+
+    #[test]
+    fn test_field_as_header() {
+        let schema = Schema::default();
+        assert_eq!(
+            schema.field(" Дата  окончания подачи заявок "),
+            Some(Field::CollectingDate)
+        );
+        assert_eq!(schema.field("статус"), Some(Field::Status));
+        assert_eq!(schema.field("Расчёт"), Some(Field::Estimation));
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        assert!(toml::from_str::<Schema>("status = []").is_err());
+        assert!(toml::from_str::<Schema>("unknown_field = [\"Поле\"]").is_err());
+        assert!(toml::from_str::<Schema>("status = [\"Регион\"]").is_err());
+    }
+
+    #[test]
+    fn test_is_active() {
+        let statuses = Statuses::default();
+        assert_eq!(statuses.active(STATUS_GO), Some(STATUS_GO));
+        assert_eq!(statuses.active(STATUS_ADMITTED), Some(STATUS_ADMITTED));
+        assert_eq!(statuses.active(STATUS_APPLY), Some(STATUS_APPLY));
+        assert_eq!(statuses.active(STATUS_WIN), Some(STATUS_WIN));
+        assert_eq!(statuses.active(STATUS_LOSS), Some(STATUS_LOSS));
+        assert_eq!(statuses.active(STATUS_ESTIMATION), Some(STATUS_ESTIMATION));
+        assert_eq!(statuses.active("invalid status"), None);
+        assert_eq!(statuses.active(STATUS_NOT_GO), None);
+    }
+
+    #[test]
+    fn test_is_active_normalized() {
+        let mut statuses = Statuses::default();
+        assert_eq!(statuses.active("Идем "), Some(STATUS_GO));
+        assert_eq!(statuses.active("ИДЁМ"), Some(STATUS_GO));
+        assert_eq!(statuses.active(" не  выиграли"), Some(STATUS_LOSS));
+
+        statuses.ignore_case = false;
+        assert_eq!(statuses.active("Идем "), None);
+        assert_eq!(statuses.active("идем "), Some(STATUS_GO));
+
+        statuses.ignore_whitespace = false;
+        assert_eq!(statuses.active("идем "), None);
+    }
+
+    #[test]
+    fn test_statuses_validate() {
+        assert!(Statuses::default().validate().is_ok());
+
+        let statuses: Statuses = toml::from_str("active = []").unwrap();
+        assert!(statuses.validate().is_err());
+
+        let statuses: Statuses = toml::from_str("active = [\"идем\"]\nremoved = \"Идем\"").unwrap();
+        assert!(statuses.validate().is_err());
+    }
+}
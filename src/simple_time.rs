@@ -1,4 +1,6 @@
+use serde::{de, Deserialize, Deserializer};
 use std::fmt;
+use std::str::FromStr;
 use std::time::{Duration, SystemTime};
 
 const JANUARY: (u32, &str, u32) = (1, "January", 31);
@@ -14,6 +16,9 @@ const OCTOBER: (u32, &str, u32) = (10, "October", 31);
 const NOVEMBER: (u32, &str, u32) = (11, "November", 30);
 const DECEMBER: (u32, &str, u32) = (12, "December", 31);
 
+// offsets are whole minutes and no further than that from UTC
+const MAX_OFFSET_MINUTES: i32 = 14 * 60;
+
 /// Returns option of month tuple. If number of month is not in a range of 1..12 then returns None
 #[cfg(test)]
 pub fn month<'a>(month: usize) -> Option<(u32, &'a str, u32)> {
@@ -34,7 +39,8 @@ pub fn month<'a>(month: usize) -> Option<(u32, &'a str, u32)> {
     }
 }
 
-/// Struct built from SystemTime (now or from passed in). It is GMT time, no local offsets.
+/// Struct built from SystemTime (now or from passed in). It is GMT time
+/// unless the offset is set, in that case it's the local time of that offset
 #[derive(Debug)]
 pub struct Moment {
     pub year: u64,
@@ -43,24 +49,25 @@ pub struct Moment {
     pub hours: u64,
     pub minutes: u64,
     pub seconds: u64,
+    #[allow(dead_code)]
     pub is_leap_year: bool,
+    pub offset: Offset,
 }
 
 impl Moment {
     /// Returns option of Moment from now
-    #[allow(dead_code)]
     pub fn now() -> Option<Self> {
         SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .ok()
-            .and_then(|d| Some(Self::from_duration_since_epoch(d)))
+            .map(Self::from_duration_since_epoch)
     }
 
     /// Returns option of Moment. None if timestamp is before UNIX epoch
     pub fn from_sys_time(time: SystemTime) -> Option<Self> {
         time.duration_since(SystemTime::UNIX_EPOCH)
             .ok()
-            .and_then(|d| Some(Self::from_duration_since_epoch(d)))
+            .map(Self::from_duration_since_epoch)
     }
 
     pub const fn from_duration_since_epoch(dse: Duration) -> Self {
@@ -87,6 +94,28 @@ impl Moment {
             minutes: mins,
             seconds: secs,
             is_leap_year,
+            offset: Offset::UTC,
+        }
+    }
+
+    /// Returns Moment of seconds since the epoch, that may be negative
+    /// for the dates before 1970 unlike the Duration
+    pub const fn from_seconds_since_epoch(secs: i64) -> Self {
+        let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+        let sec_in_day = secs.rem_euclid(86400) as u64;
+        let hrs = hours(sec_in_day);
+        let mins = minutes(sec_in_day, hrs);
+
+        Self {
+            // excel dates don't go before the common era
+            year: if year < 0 { 0 } else { year as u64 },
+            month,
+            day,
+            hours: hrs,
+            minutes: mins,
+            seconds: seconds(sec_in_day, hrs, mins),
+            is_leap_year: days_in_month(year, FEBRUARY.0 as u64) == 29,
+            offset: Offset::UTC,
         }
     }
 
@@ -103,20 +132,121 @@ impl Moment {
 impl fmt::Display for Moment {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // we want to build rfc3339 format string
-        // like "2006-01-02T15:04:05Z00:00"
+        // like "2006-01-02T15:04:05+03:00"
         write!(
             f,
-            "{}-{}-{}T{}:{}:{}+00:00",
+            "{}-{}-{}T{}:{}:{}{}",
             self.year,
             Moment::add_leading_zero(self.month),
             Moment::add_leading_zero(self.day),
             Moment::add_leading_zero(self.hours),
             Moment::add_leading_zero(self.minutes),
             Moment::add_leading_zero(self.seconds),
+            self.offset,
         )
     }
 }
 
+/// Fixed offset from UTC in minutes e.g. Moscow time is +03:00
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Offset(i32);
+
+impl Offset {
+    pub const UTC: Offset = Offset(0);
+    // Moscow time that is the base of russian time zones e.g. 'МСК+4'
+    pub const MSK: Offset = Offset(3 * 60);
+
+    /// Returns the offset if it's no further than 14 hours from UTC
+    pub const fn from_minutes(minutes: i32) -> Option<Self> {
+        if minutes.abs() > MAX_OFFSET_MINUTES {
+            return None;
+        }
+        Some(Offset(minutes))
+    }
+
+    pub const fn seconds(self) -> i64 {
+        self.0 as i64 * 60
+    }
+}
+
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { '-' } else { '+' };
+        let abs = self.0.abs();
+        write!(
+            f,
+            "{}{}:{}",
+            sign,
+            Moment::add_leading_zero((abs / 60) as u64),
+            Moment::add_leading_zero((abs % 60) as u64),
+        )
+    }
+}
+
+/// Parses '+03:00', '+0300', '+3', 'UTC+3', 'Z' as well as russian
+/// zones that are counted from Moscow time e.g. 'МСК', 'МСК+4' or 'MSK-1'
+impl FromStr for Offset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid UTC offset '{}'", s);
+        let lower = s.trim().to_lowercase();
+
+        let (base, rest) = match lower.as_str() {
+            "z" | "utc" | "gmt" => return Ok(Offset::UTC),
+            l => match ["мск", "msk"].iter().find_map(|p| l.strip_prefix(p)) {
+                Some(rest) => (Offset::MSK, rest),
+                None => (
+                    Offset::UTC,
+                    ["utc", "gmt"]
+                        .iter()
+                        .find_map(|p| l.strip_prefix(p))
+                        .unwrap_or(l),
+                ),
+            },
+        };
+        if rest.is_empty() {
+            return Ok(base);
+        }
+
+        let (sign, rest) = if let Some(r) = rest.strip_prefix('+') {
+            (1, r)
+        } else if let Some(r) = rest.strip_prefix(['-', '−']) {
+            (-1, r)
+        } else {
+            return Err(err());
+        };
+        if !rest.is_ascii() {
+            return Err(err());
+        }
+        let (hours, minutes) = match rest.split_once(':') {
+            Some(hm) => hm,
+            None if rest.len() == 4 => rest.split_at(2),
+            None => (rest, "0"),
+        };
+        if !(1..=2).contains(&hours.len()) || minutes.len() > 2 {
+            return Err(err());
+        }
+        let hours: i32 = hours.parse().map_err(|_| err())?;
+        let minutes: i32 = minutes.parse().map_err(|_| err())?;
+        if minutes >= 60 {
+            return Err(err());
+        }
+
+        Offset::from_minutes(base.0 + sign * (hours * 60 + minutes)).ok_or_else(err)
+    }
+}
+
+impl<'de> Deserialize<'de> for Offset {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
 /// Unix timestamp / hours in a year to get years from 1970 to timestamp
 const fn year(timestamp_secs: u64) -> u64 {
     //approximately 31536000 hours in a year
@@ -167,6 +297,53 @@ const fn seconds(seconds_in_day: u64, hours: u64, minutes: u64) -> u64 {
     (seconds_in_day - (hours * 3600)) - (minutes * 60)
 }
 
+/// Returns number of days since the epoch of the civil date.
+/// The date is expected to be valid, e.g. month in a range of 1..12
+pub const fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    // years start from March, so February is the last month of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = (year - era * 400) as u64;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era as i64 - 719468
+}
+
+/// Returns the civil date (year, month, day) of the number of days since the epoch.
+/// It's the inverse of days_from_civil, so the days may be negative
+pub const fn civil_from_days(days: i64) -> (i64, u64, u64) {
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = (days - era * 146097) as u64;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era as i64 + era * 400;
+    // years start from March, so January and February are in the next civil year
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// Returns number of days in the month of the civil year
+/// or 0 if month is not in a range of 1..12
+pub const fn days_in_month(year: i64, month: u64) -> u64 {
+    let is_leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if is_leap_year => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => 0,
+    }
+}
+
 /// Returns the month and day of that month
 const fn day_and_month(days_in_current_year: u64, is_leap_year: bool) -> (u64, u64) {
     let add_day = is_leap_year as u64;
@@ -207,8 +384,33 @@ mod tests {
             minutes: 1,
             seconds: 44,
             is_leap_year: false,
+            offset: Offset::UTC,
         };
         assert_eq!(m.to_string(), "2021-11-10T12:01:44+00:00".to_string());
+
+        let m = Moment {
+            offset: Offset::MSK,
+            ..m
+        };
+        assert_eq!(m.to_string(), "2021-11-10T12:01:44+03:00".to_string());
+    }
+
+    #[test]
+    fn test_offset() {
+        let offset = |s: &str| s.parse::<Offset>().map(|o| o.to_string());
+        assert_eq!(offset("+03:00"), Ok("+03:00".to_owned()));
+        assert_eq!(offset("+0530"), Ok("+05:30".to_owned()));
+        assert_eq!(offset("UTC+7"), Ok("+07:00".to_owned()));
+        assert_eq!(offset("-03:30"), Ok("-03:30".to_owned()));
+        assert_eq!(offset("Z"), Ok("+00:00".to_owned()));
+        assert_eq!(offset("МСК"), Ok("+03:00".to_owned()));
+        assert_eq!(offset("МСК+4"), Ok("+07:00".to_owned()));
+        assert_eq!(offset("msk-1"), Ok("+02:00".to_owned()));
+        assert!(offset("+15:00").is_err());
+        assert!(offset("+03:60").is_err());
+        assert!(offset("03:00").is_err());
+        assert!(offset("+аб").is_err());
+        assert!(offset("Europe/Moscow").is_err());
     }
 
     #[test]
@@ -330,6 +532,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_days_from_civil() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(2021, 11, 16), 18947);
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11017), (2000, 3, 1));
+        assert_eq!(civil_from_days(18947), (2021, 11, 16));
+        assert_eq!(civil_from_days(-25567), (1900, 1, 1));
+        for days in -30000..30000 {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+    }
+
+    #[test]
+    fn test_from_seconds_since_epoch() {
+        let m = Moment::from_seconds_since_epoch(1583020799);
+        assert_eq!(
+            (2020, 2, 29, 23, 59, 59),
+            (m.year, m.month, m.day, m.hours, m.minutes, m.seconds)
+        );
+        let m = Moment::from_seconds_since_epoch(-1);
+        assert_eq!(
+            (1969, 12, 31, 23, 59, 59),
+            (m.year, m.month, m.day, m.hours, m.minutes, m.seconds)
+        );
+        let m = Moment::from_seconds_since_epoch(-2203891200);
+        assert_eq!(
+            (1900, 3, 1, 0, 0, 0),
+            (m.year, m.month, m.day, m.hours, m.minutes, m.seconds)
+        );
+        assert!(!m.is_leap_year);
+    }
+
+    #[test]
+    fn test_days_in_month() {
+        assert_eq!(days_in_month(2021, 11), 30);
+        assert_eq!(days_in_month(2021, 2), 28);
+        assert_eq!(days_in_month(2020, 2), 29);
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(2021, 13), 0);
+    }
+
     #[test]
     fn test_extra_days() {
         assert_eq!(14, extra_days(2025));
@@ -345,6 +598,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_is_leap_year() {
         assert_eq!(true, is_leap_year(2020));
         assert_eq!(false, is_leap_year(2021));
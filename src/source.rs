@@ -0,0 +1,432 @@
+use crate::{
+    config::WorkbookConfig,
+    excel::{self, CheckedRecords, ColumnsCheck, Purchase, WorkbookError},
+    payload::Envelope,
+};
+use calamine::{DataType, Range};
+use encoding_rs::Encoding;
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+const DEFAULT_CSV_DELIMITER: char = ';';
+const DEFAULT_CSV_ENCODING: &str = "utf-8";
+
+/// Alias result type for this module
+type SourceResult<T> = std::result::Result<T, WorkbookError>;
+
+/// Source of the registry records. Every source produces
+/// the same [Purchase]'s, so the rest of the program
+/// doesn't care where they came from
+pub trait Source {
+    /// Returns active state records if any
+    fn active_state(&self) -> SourceResult<Option<Vec<Purchase>>> {
+        Ok(self.active_state_checked()?.0)
+    }
+
+    /// Returns active state records with the report
+    /// of the cells which values can't be read
+    fn active_state_checked(&self) -> SourceResult<CheckedRecords>;
+
+    /// Checks that the source has all the columns expected by schema.
+    /// Sources with multiple sheets are checked sheet by sheet
+    fn check_columns(&self) -> SourceResult<Vec<ColumnsCheck>>;
+}
+
+/// Kind of the registry source
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Excel,
+    Csv,
+    Json,
+}
+
+impl SourceKind {
+    /// Detects kind of the source by the file extension.
+    /// Anything that is not csv or json is considered a workbook
+    pub fn detect(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .as_deref()
+        {
+            Some("csv") | Some("txt") => SourceKind::Csv,
+            Some("json") => SourceKind::Json,
+            _ => SourceKind::Excel,
+        }
+    }
+}
+
+/// Settings of the csv source
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    // any label from the Encoding Standard e.g. 'windows-1251' or 'cp1251'
+    pub encoding: String,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: DEFAULT_CSV_DELIMITER,
+            encoding: DEFAULT_CSV_ENCODING.to_owned(),
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Checks that csv settings make sense
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.delimiter.is_ascii() {
+            return Err(format!("delimiter '{}' is not ascii", self.delimiter));
+        }
+        if Encoding::for_label(self.encoding.as_bytes()).is_none() {
+            return Err(format!("unknown encoding '{}'", self.encoding));
+        }
+        Ok(())
+    }
+
+    fn encoding(&self) -> &'static Encoding {
+        Encoding::for_label(self.encoding.as_bytes()).unwrap_or(encoding_rs::UTF_8)
+    }
+}
+
+/// Returns the source of the records that is set by config
+/// or detected by the extension of the path
+pub fn open(config: &WorkbookConfig) -> Box<dyn Source + '_> {
+    let kind = config
+        .source
+        .unwrap_or_else(|| SourceKind::detect(Path::new(&config.path)));
+
+    match kind {
+        SourceKind::Excel => Box::new(ExcelSource { config }),
+        SourceKind::Csv => Box::new(CsvSource { config }),
+        SourceKind::Json => Box::new(JsonSource { config }),
+    }
+}
+
+/// Spreadsheet in any format that is supported by calamine
+pub struct ExcelSource<'a> {
+    config: &'a WorkbookConfig,
+}
+
+impl Source for ExcelSource<'_> {
+    fn active_state(&self) -> SourceResult<Option<Vec<Purchase>>> {
+        excel::active_state(Path::new(&self.config.path), self.config)
+    }
+
+    fn active_state_checked(&self) -> SourceResult<CheckedRecords> {
+        excel::active_state_checked(Path::new(&self.config.path), self.config)
+    }
+
+    fn check_columns(&self) -> SourceResult<Vec<ColumnsCheck>> {
+        excel::check_columns(Path::new(&self.config.path), self.config)
+    }
+}
+
+/// Csv file with the header row. Columns are located by headers
+pub struct CsvSource<'a> {
+    config: &'a WorkbookConfig,
+}
+
+impl CsvSource<'_> {
+    /// Reads the whole csv file to the range
+    /// as if it was a sheet of the workbook
+    fn range(&self) -> SourceResult<Range<DataType>> {
+        let bytes = fs::read(&self.config.path)?;
+        csv_bytes_range(&bytes, &self.config.csv)
+    }
+}
+
+impl Source for CsvSource<'_> {
+    fn active_state_checked(&self) -> SourceResult<CheckedRecords> {
+        excel::active_state_range(self.range()?, self.config)
+    }
+
+    fn check_columns(&self) -> SourceResult<Vec<ColumnsCheck>> {
+        Ok(vec![excel::check_range_columns(self.range()?, self.config)])
+    }
+}
+
+/// Json file with the records in the same format as the snapshot
+/// i.e. an envelope or an array of records
+pub struct JsonSource<'a> {
+    config: &'a WorkbookConfig,
+}
+
+impl Source for JsonSource<'_> {
+    fn active_state_checked(&self) -> SourceResult<CheckedRecords> {
+        let s = fs::read_to_string(&self.config.path)?;
+        json_records(&s, self.config)
+    }
+
+    fn check_columns(&self) -> SourceResult<Vec<ColumnsCheck>> {
+        // every field is checked by deserialization
+        Ok(Vec::new())
+    }
+}
+
+/// Returns active records of the json text
+fn json_records(s: &str, config: &WorkbookConfig) -> SourceResult<CheckedRecords> {
+    let purches = Envelope::<Purchase>::from_json(s)?.records;
+    excel::active_records(purches, config)
+}
+
+/// Decodes csv file content by the encoding of the options
+/// and parses it to the range of string cells
+fn csv_bytes_range(bytes: &[u8], options: &CsvOptions) -> SourceResult<Range<DataType>> {
+    let (text, _, _) = options.encoding().decode(bytes);
+    csv_range(&text, options.delimiter as u8)
+}
+
+/// Parses csv text to the range of string cells
+fn csv_range(text: &str, delimiter: u8) -> SourceResult<Range<DataType>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        rows.push(record?);
+    }
+
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or_default();
+    if rows.is_empty() || width == 0 {
+        return Ok(Range::empty());
+    }
+
+    let mut range = Range::new((0, 0), (rows.len() as u32 - 1, width as u32 - 1));
+    for (i, row) in rows.iter().enumerate() {
+        for (j, value) in row.iter().enumerate() {
+            if !value.is_empty() {
+                range.set_value((i as u32, j as u32), DataType::String(value.to_owned()));
+            }
+        }
+    }
+
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(SourceKind::detect(Path::new("reg.xlsx")), SourceKind::Excel);
+        assert_eq!(SourceKind::detect(Path::new("reg.ods")), SourceKind::Excel);
+        assert_eq!(SourceKind::detect(Path::new("reg")), SourceKind::Excel);
+        assert_eq!(SourceKind::detect(Path::new("reg.CSV")), SourceKind::Csv);
+        assert_eq!(SourceKind::detect(Path::new("reg.json")), SourceKind::Json);
+    }
+
+    #[test]
+    fn test_csv_options_validate() {
+        assert!(CsvOptions::default().validate().is_ok());
+
+        let options = CsvOptions {
+            delimiter: '\t',
+            encoding: "cp1251".to_owned(),
+        };
+        assert!(options.validate().is_ok());
+        assert_eq!(options.encoding(), encoding_rs::WINDOWS_1251);
+
+        let options = CsvOptions {
+            delimiter: '¦',
+            ..CsvOptions::default()
+        };
+        assert!(options.validate().is_err());
+
+        let options = CsvOptions {
+            encoding: "koi9".to_owned(),
+            ..CsvOptions::default()
+        };
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn test_csv_range() {
+        let range = csv_range("Номер;Статус\n0373;\"идем; срочно\"\n0374\n", b';').unwrap();
+        assert_eq!(range.get_size(), (3, 2));
+        assert_eq!(
+            range.get_value((1, 1)),
+            Some(&DataType::String("идем; срочно".to_owned()))
+        );
+        assert_eq!(range.get_value((2, 1)), Some(&DataType::Empty));
+
+        assert!(csv_range("", b';').unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_windows_1251() {
+        let (bytes, _, _) = encoding_rs::WINDOWS_1251.encode("Статус;идем");
+        let options = CsvOptions {
+            encoding: "windows-1251".to_owned(),
+            ..CsvOptions::default()
+        };
+        let (text, _, _) = options.encoding().decode(&bytes);
+        assert_eq!(text, "Статус;идем");
+    }
+
+    /// Returns the values of the record fields
+    fn fields(purches: &[Purchase], names: &[&str]) -> Vec<Vec<String>> {
+        purches
+            .iter()
+            .map(|p| {
+                let json = serde_json::to_value(p).unwrap();
+                names
+                    .iter()
+                    .map(|n| match &json[*n] {
+                        serde_json::Value::String(s) => s.clone(),
+                        v => v.to_string(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_csv_source() {
+        let text = "Номер;Статус;Дата_проведения_аукциона_конкурса;Регион;НМЦК\n\
+                    № 0373100000121000001;Идем;20.10.2099;Москва;1 234 567,89\n\
+                    0373100000121000002;не идем;21.10.2099;Тверь;100\n\
+                    0373100000121000003;заявлены;22.10.2099;Тверь;\n";
+        let (bytes, _, _) = encoding_rs::WINDOWS_1251.encode(text);
+        let config: WorkbookConfig = toml::from_str("csv = { encoding = 'windows-1251' }").unwrap();
+
+        let range = csv_bytes_range(&bytes, &config.csv).unwrap();
+        let (purches, issues) = excel::active_state_range(range, &config).unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(
+            fields(
+                &purches.unwrap(),
+                &["registry_number", "status", "region", "max_price", "row"]
+            ),
+            [
+                ["0373100000121000001", "идем", "Москва", "1234567.89", "2"],
+                ["0373100000121000003", "заявлены", "Тверь", "0.0", "4"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_csv_headers() {
+        // the title above the header row and the header names are set by config
+        let text = "Реестр закупок,,\n\
+                    № извещения,Статус,Дата торгов\n\
+                    0373100000121000001,идем,20.10.2099\n";
+        let config: WorkbookConfig = toml::from_str(
+            r#"
+            header_row = 2
+            csv = { delimiter = "," }
+            headers = { registry_number = ["№ извещения"], bidding_date = ["Дата торгов"] }
+        "#,
+        )
+        .unwrap();
+
+        let range = csv_bytes_range(text.as_bytes(), &config.csv).unwrap();
+        let (purches, _) = excel::active_state_range(range, &config).unwrap();
+        assert_eq!(
+            fields(
+                &purches.unwrap(),
+                &["registry_number", "bidding_datetime", "row"]
+            ),
+            [["0373100000121000001", "2099-10-20T00:00:00+03:00", "3"]]
+        );
+
+        // the headers are missing at the default row
+        let config = WorkbookConfig {
+            header_row: 1,
+            ..config
+        };
+        let range = csv_bytes_range(text.as_bytes(), &config.csv).unwrap();
+        assert!(matches!(
+            excel::active_state_range(range, &config),
+            Err(WorkbookError::SchemaError(_))
+        ));
+    }
+
+    #[test]
+    fn test_json_records() {
+        let record = |num: &str, status: &str| {
+            serde_json::json!({
+                "registry_number": num,
+                "purchase_subject": "", "purchase_abbr": "", "purchase_type": "",
+                "collecting_datetime": "", "approval_datetime": "",
+                "bidding_datetime": "2099-10-20T07:00:00Z",
+                "region": "Москва", "customer_type": "",
+                "max_price": 0.0, "application_guarantee": 0.0, "contract_guarantee": 0.0,
+                "status": status, "our_participants": "", "estimation": 0.0,
+                "etp": "", "winner": "", "winner_price": 0.0, "participants": "",
+            })
+        };
+        let records = serde_json::json!([
+            record("0373100000121000001", "Идем"),
+            record("0373100000121000002", "не идем"),
+            record("0373100000121000003", "заявлены"),
+        ]);
+        let config = WorkbookConfig {
+            path: "reg.json".to_owned(),
+            ..WorkbookConfig::default()
+        };
+
+        let names = ["registry_number", "status", "path", "row"];
+        let expected = [
+            ["0373100000121000001", "идем", "reg.json", "1"],
+            ["0373100000121000003", "заявлены", "reg.json", "3"],
+        ];
+        let (purches, issues) = json_records(&records.to_string(), &config).unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(fields(&purches.unwrap(), &names), expected);
+
+        // the same records in the envelope
+        let envelope = serde_json::json!({
+            "version": 1,
+            "generated_at": "2099-10-17T07:00:00+00:00",
+            "workbook": { "path": "reg.json" },
+            "sequence": 3,
+            "kind": "snapshot",
+            "records": records,
+        });
+        let (purches, _) = json_records(&envelope.to_string(), &config).unwrap();
+        assert_eq!(fields(&purches.unwrap(), &names), expected);
+
+        assert!(json_records("{\"records\": 1}", &config).is_err());
+
+        // the limit is of the records that are read, not of the active ones
+        let config = WorkbookConfig {
+            max_rows: 2,
+            ..config
+        };
+        let (purches, _) = json_records(&records.to_string(), &config).unwrap();
+        assert_eq!(fields(&purches.unwrap(), &names), expected[..1]);
+
+        // active records that are lost are reported
+        let mut records = serde_json::json!([
+            record("0373100000121000001", "идем"),
+            record("", "идем"),
+            record("0373100000121000003", "идем"),
+            record("0373100000121000004", "не идем"),
+        ]);
+        records[2]["bidding_datetime"] = "20.10.2099 10:00".into();
+        records[3]["bidding_datetime"] = "".into();
+        let config = WorkbookConfig {
+            max_rows: 7000,
+            ..config
+        };
+        let (purches, issues) = json_records(&records.to_string(), &config).unwrap();
+        assert_eq!(purches.unwrap().len(), 1);
+        let issues: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            issues,
+            [
+                "cell 2 (registry_number): missing value ''",
+                "cell 3 (bidding_datetime): invalid date '20.10.2099 10:00'",
+            ]
+        );
+    }
+}